@@ -0,0 +1,331 @@
+//! Access tokens for private feeds.
+//!
+//! When a secret is configured, every URL of a channel feed (enclosures, artwork, and the
+//! feed itself) carries a token derived from the secret with HMAC-SHA256. Tokens are derived
+//! per channel, or per subscriber when subscribers are listed, in which case each subscriber
+//! gets a feed of their own. Checking the tokens is left to the web server.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use url::Url;
+
+use crate::{Result, error::Error};
+
+/// Where the token is placed in URLs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Placement {
+    /// In a query parameter, e.g. `https://example.com/channel/episode.mp4?token=…`.
+    #[default]
+    Query,
+    /// As the first path segment after the base URL, e.g.
+    /// `https://example.com/<token>/channel/episode.mp4`.
+    Path,
+}
+
+/// Access settings of a channel.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// HMAC secret from which tokens are derived. Feeds are public when unset.
+    pub secret: Option<String>,
+    /// Where the token is placed in URLs.
+    pub placement: Placement,
+    /// Name of the query parameter, used by [`Placement::Query`].
+    pub query_param: String,
+    /// Subscriber names. When empty, a single token is derived for the channel.
+    pub subscribers: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            placement: Placement::Query,
+            query_param: "token".into(),
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+/// A feed to publish for a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feed {
+    /// Subscriber of the feed, if tokens are derived per subscriber.
+    pub subscriber: Option<String>,
+    /// Access token, if the feed is private.
+    pub token: Option<String>,
+}
+
+impl Feed {
+    /// Return the filepath of the feed for the channel directory `dirpath`, with the given
+    /// extension: `<dir>.<ext>`, or `<dir>.<subscriber>.<ext>` for a subscriber feed.
+    pub fn filepath<P: AsRef<Path>>(&self, dirpath: P, extension: &str) -> PathBuf {
+        let mut filename = dirpath.as_ref().as_os_str().to_os_string();
+        if let Some(subscriber) = &self.subscriber {
+            filename.push(format!(".{subscriber}"));
+        }
+        filename.push(format!(".{extension}"));
+        filename.into()
+    }
+}
+
+impl Settings {
+    /// Return the feeds to publish for the channel directory named `channel`.
+    pub fn feeds(&self, channel: &str, generations: &Generations) -> Result<Vec<Feed>> {
+        let Some(secret) = &self.secret else {
+            return Ok(vec![Feed {
+                subscriber: None,
+                token: None,
+            }]);
+        };
+
+        if self.subscribers.is_empty() {
+            let token = derive_token(secret, channel, "", generations.get(channel));
+            return Ok(vec![Feed {
+                subscriber: None,
+                token: Some(token),
+            }]);
+        }
+
+        self.subscribers
+            .iter()
+            .map(|subscriber| {
+                let valid = !subscriber.is_empty()
+                    && subscriber
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !valid {
+                    return Err(Error::ConfigError(format!(
+                        "invalid subscriber name `{subscriber}`, expected letters, digits, `-` or `_`"
+                    )));
+                }
+                let token = derive_token(secret, channel, subscriber, generations.get(subscriber));
+                Ok(Feed {
+                    subscriber: Some(subscriber.clone()),
+                    token: Some(token),
+                })
+            })
+            .collect()
+    }
+
+    /// Return the names keying the token generations of the channel directory named
+    /// `channel`: its subscribers, the channel itself without subscribers, or none for public
+    /// feeds.
+    pub fn token_names<'a>(&'a self, channel: &'a str) -> Vec<&'a str> {
+        match (&self.secret, self.subscribers.is_empty()) {
+            (None, _) => Vec::new(),
+            (Some(_), true) => vec![channel],
+            (Some(_), false) => self.subscribers.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// Return `base_url` with the `token` added according to the placement.
+    pub fn apply(&self, base_url: &Url, token: &str) -> Url {
+        let mut url = base_url.clone();
+        match self.placement {
+            Placement::Query => {
+                url.query_pairs_mut().append_pair(&self.query_param, token);
+            }
+            Placement::Path => {
+                if let Ok(mut segments) = url.path_segments_mut() {
+                    segments.pop_if_empty().push(token);
+                }
+            }
+        }
+        url
+    }
+}
+
+/// Return the token of `subscriber` (empty for a channel token) for `channel`.
+fn derive_token(secret: &str, channel: &str, subscriber: &str, generation: u32) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(format!("{channel}\n{subscriber}\n{generation}").as_bytes());
+    let digest = mac.finalize().into_bytes();
+    // 128 bits are plenty for a bearer token and keep URLs short.
+    digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Token generations, bumped to rotate tokens.
+///
+/// Keys are subscriber names, or channel directory names for channel tokens. They are stored
+/// in the data directory, in [`Generations::FILENAME`], which the web server must not serve
+/// since it lists the subscribers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Generations(BTreeMap<String, u32>);
+
+impl Generations {
+    /// Name of the file storing the generations in the data directory.
+    pub const FILENAME: &'static str = ".podfeed-tokens.json";
+
+    /// Load the generations from the data directory, if they exist.
+    pub fn load<P: AsRef<Path>>(data_dirpath: P) -> Result<Self> {
+        let filepath = data_dirpath.as_ref().join(Self::FILENAME);
+        match std::fs::read_to_string(filepath) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save the generations in the data directory.
+    pub fn save<P: AsRef<Path>>(&self, data_dirpath: P) -> Result<()> {
+        let filepath = data_dirpath.as_ref().join(Self::FILENAME);
+        std::fs::write(filepath, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Return the current generation of `name`.
+    pub fn get(&self, name: &str) -> u32 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    /// Bump the generation of `name`, which invalidates its current tokens.
+    ///
+    /// `names` are the names with tokens, see [`Settings::token_names`]: rotating any other
+    /// name, such as a misspelled subscriber, would change no token and is an error.
+    pub fn rotate(&mut self, name: &str, names: &[&str]) -> Result<()> {
+        if !names.contains(&name) {
+            let mut names = names.to_vec();
+            names.sort_unstable();
+            names.dedup();
+            return Err(Error::ConfigError(format!(
+                "cannot rotate `{name}`: not a subscriber or a channel with private feeds, expected one of: {}",
+                names.join(", ")
+            )));
+        }
+        *self.0.entry(name.to_string()).or_default() += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn private(subscribers: &[&str]) -> Settings {
+        Settings {
+            secret: Some("secret".into()),
+            subscribers: subscribers.iter().map(|s| s.to_string()).collect(),
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn public_channel_has_a_single_feed_without_token() {
+        let feeds = Settings::default()
+            .feeds("channel", &Generations::default())
+            .unwrap();
+        assert_eq!(
+            feeds,
+            vec![Feed {
+                subscriber: None,
+                token: None
+            }]
+        );
+    }
+
+    #[test]
+    fn tokens_depend_on_channel_subscriber_and_generation() {
+        let settings = private(&["alice", "bob"]);
+        let mut generations = Generations::default();
+
+        let feeds = settings.feeds("channel", &generations).unwrap();
+        let other_channel = settings.feeds("other", &generations).unwrap();
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].subscriber.as_deref(), Some("alice"));
+        assert_eq!(feeds[0].token.as_ref().unwrap().len(), 32);
+        assert_ne!(feeds[0].token, feeds[1].token);
+        assert_ne!(feeds[0].token, other_channel[0].token);
+        assert_eq!(feeds, settings.feeds("channel", &generations).unwrap());
+
+        generations.rotate("alice", &["alice", "bob"]).unwrap();
+        let rotated = settings.feeds("channel", &generations).unwrap();
+        assert_ne!(rotated[0].token, feeds[0].token);
+        assert_eq!(rotated[1].token, feeds[1].token);
+    }
+
+    #[test]
+    fn invalid_subscriber_names_are_rejected() {
+        let error = private(&["a b"])
+            .feeds("channel", &Generations::default())
+            .unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.contains("a b")));
+    }
+
+    #[test]
+    fn only_names_with_tokens_are_rotated() {
+        assert!(Settings::default().token_names("channel").is_empty());
+        assert_eq!(private(&[]).token_names("channel"), ["channel"]);
+        let settings = private(&["alice", "bob"]);
+        assert_eq!(settings.token_names("channel"), ["alice", "bob"]);
+
+        let mut generations = Generations::default();
+        let error = generations
+            .rotate("alcie", &settings.token_names("channel"))
+            .unwrap_err();
+        assert!(
+            matches!(error, Error::ConfigError(msg) if msg.contains("`alcie`") && msg.ends_with("alice, bob"))
+        );
+        assert_eq!(generations, Generations::default());
+    }
+
+    #[test]
+    fn apply_places_token_in_query_or_path() {
+        let base_url = Url::parse("https://cdn.example.com/podcasts/?v=1").unwrap();
+
+        let query = Settings {
+            query_param: "key".into(),
+            ..private(&[])
+        };
+        assert_eq!(
+            query.apply(&base_url, "abc").as_str(),
+            "https://cdn.example.com/podcasts/?v=1&key=abc"
+        );
+
+        let path = Settings {
+            placement: Placement::Path,
+            ..private(&[])
+        };
+        assert_eq!(
+            path.apply(&base_url, "abc").as_str(),
+            "https://cdn.example.com/podcasts/abc?v=1"
+        );
+    }
+
+    #[test]
+    fn feed_filepath_includes_subscriber() {
+        let feed = Feed {
+            subscriber: Some("alice".into()),
+            token: Some("abc".into()),
+        };
+        assert_eq!(
+            feed.filepath("/data/channel", "xml"),
+            PathBuf::from("/data/channel.alice.xml")
+        );
+    }
+
+    #[test]
+    fn generations_round_trip_through_data_dir() {
+        let directory = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Generations::load(directory.path()).unwrap(),
+            Generations::default()
+        );
+
+        let mut generations = Generations::default();
+        generations.rotate("alice", &["alice"]).unwrap();
+        generations.rotate("alice", &["alice"]).unwrap();
+        generations.save(directory.path()).unwrap();
+
+        let loaded = Generations::load(directory.path()).unwrap();
+        assert_eq!(loaded.get("alice"), 2);
+        assert_eq!(loaded.get("bob"), 0);
+    }
+}
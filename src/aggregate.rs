@@ -0,0 +1,252 @@
+//! Virtual feeds merging the episodes of several channel directories.
+//!
+//! Virtual feeds are defined in `[virtual."<name>"]` tables and written next to the channel
+//! feeds, as `<name>.xml`:
+//!
+//! ```toml
+//! [virtual."All cooking"]
+//! title = "All my cooking channels"
+//! description = "Every cooking episode, newest first."
+//! artwork = "cooking.jpg"
+//! channels = ["Cooking", "Baking"]
+//! ```
+//!
+//! The episodes are converted with the settings of their own channel directory, and the feed
+//! itself with the settings of `[channels."<name>"]`, if any.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    Result,
+    convert::{self, ParsedChannel, Stats},
+    error::Error,
+    rss,
+    settings::Settings,
+    source::{self, Sources},
+};
+
+/// Definition of a virtual feed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Feed {
+    /// Title of the feed.
+    pub title: String,
+    /// Description of the feed.
+    #[serde(default)]
+    pub description: String,
+    /// Image of the feed, relative to the data directory.
+    pub artwork: PathBuf,
+    /// Names of the channel directories whose episodes are merged.
+    pub channels: Vec<String>,
+    /// Author of the feed, the title by default.
+    pub author: Option<String>,
+    /// Link of the feed, the base URL by default.
+    pub link: Option<String>,
+}
+
+/// Merge the episodes of the channel directories of `feed`, newest first, keeping the first
+/// episode with a given YouTube ID, and return the feed with figures about its episodes.
+///
+/// `name` is the name of the virtual feed, `settings` the settings of all channels, and
+/// `sources` the metadata sources they select.
+pub async fn process<P: AsRef<Path>>(
+    base_dir: P,
+    name: &str,
+    feed: &Feed,
+    base_url: &Url,
+    settings: &Settings,
+    sources: &Sources,
+) -> Result<(rss::Rss, Stats)> {
+    let mut parsed = parse(base_dir.as_ref(), name, feed, settings, sources).await?;
+    let parse_stats = std::mem::take(&mut parsed.stats);
+    let (rss, mut stats) = parsed.feed(base_dir.as_ref(), base_url, None)?;
+    stats.merge(parse_stats);
+    Ok((rss, stats))
+}
+
+/// Parse the episodes of the channel directories of `feed` once, newest first, keeping the
+/// first episode with a given YouTube ID, and derive the artwork of the feed.
+pub(crate) async fn parse<'a>(
+    base_dir: &Path,
+    name: &str,
+    feed: &Feed,
+    settings: &'a Settings,
+    sources: &Sources,
+) -> Result<ParsedChannel<'a>> {
+    let mut stats = Stats::default();
+    let mut ids = HashSet::new();
+    let mut episodes = Vec::new();
+    for channel in &feed.channels {
+        let dirpath = base_dir.join(channel);
+        if !smol::fs::metadata(&dirpath)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Err(Error::ConfigError(format!(
+                "virtual.{name}: no channel directory `{channel}`"
+            )));
+        }
+        let channel_settings = settings.channel(channel);
+        let source = sources.get(channel_settings)?;
+        episodes.extend(
+            convert::parse_episodes(
+                source.as_ref(),
+                &dirpath,
+                channel_settings,
+                &mut ids,
+                &mut stats,
+            )
+            .await?,
+        );
+    }
+
+    // Sort episodes by upload date, newest first, in the order of the channels on ties.
+    episodes.sort_by_key(|parsed| std::cmp::Reverse(parsed.episode.upload_date));
+    let upload_date = episodes
+        .first()
+        .map(|parsed| parsed.episode.upload_date)
+        .unwrap_or_else(|| Local::now().date_naive());
+
+    let feed_settings = settings.channel(name);
+    let mut channel = source::Channel {
+        upload_date,
+        title: feed.title.clone(),
+        description: feed.description.clone(),
+        link: feed.link.clone(),
+        author: feed.author.clone().unwrap_or_else(|| feed.title.clone()),
+        language: None,
+    };
+    feed_settings
+        .itunes
+        .fill_language(&mut channel, episodes.iter().map(|parsed| &parsed.episode));
+
+    // Resize the image to a square, e.g. with the "1400x1400" suffix.
+    let artwork_filepath = stats.derive_artwork(
+        &base_dir.join(&feed.artwork),
+        &feed_settings.artwork.channel,
+    )?;
+
+    Ok(ParsedChannel {
+        channel,
+        dirpath: None,
+        artwork_filepath,
+        split_artwork_filepaths: BTreeMap::new(),
+        episodes,
+        settings: feed_settings,
+        stats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a channel directory in `data_dir` with an episode for each `(id, upload_date)`.
+    fn channel(data_dir: &Path, name: &str, episodes: &[(&str, &str)]) {
+        let channel_dir = data_dir.join(name);
+        std::fs::create_dir(&channel_dir).unwrap();
+        for (id, upload_date) in episodes {
+            let stem = format!("{upload_date}--{id}--Episode");
+            std::fs::write(
+                channel_dir.join(format!("{stem}.info.json")),
+                serde_json::json!({
+                    "id": id,
+                    "upload_date": upload_date,
+                    "playlist_index": 1,
+                    "title": format!("{name} {id}"),
+                    "webpage_url": format!("https://youtube.com/watch?v={id}"),
+                    "description": "Episode description",
+                    "channel": name,
+                    "duration": 60
+                })
+                .to_string(),
+            )
+            .unwrap();
+            std::fs::write(channel_dir.join(format!("{stem}.mp4")), [0_u8; 3]).unwrap();
+            image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+                .save(channel_dir.join(format!("{stem}.png")))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn episodes_are_merged_by_date_and_deduplicated() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(
+            data_dir.path(),
+            "Cooking",
+            &[("aaaaaaaaaaa", "20230101"), ("ccccccccccc", "20230301")],
+        );
+        channel(
+            data_dir.path(),
+            "Baking",
+            &[("aaaaaaaaaaa", "20230101"), ("bbbbbbbbbbb", "20230201")],
+        );
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+            .save(data_dir.path().join("cooking.jpg"))
+            .unwrap();
+
+        let feed: Feed = toml::from_str(
+            r#"
+            title = "All cooking"
+            artwork = "cooking.jpg"
+            channels = ["Cooking", "Baking"]
+            "#,
+        )
+        .unwrap();
+        let base_url = convert::parse_base_url("https://cdn.example.com").unwrap();
+        let (rss, stats) = smol::block_on(process(
+            data_dir.path(),
+            "All cooking",
+            &feed,
+            &base_url,
+            &Settings::default(),
+            &Sources::default(),
+        ))
+        .unwrap();
+
+        let titles: Vec<&str> = rss
+            .channel
+            .episodes
+            .iter()
+            .map(|episode| episode.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                "Cooking ccccccccccc",
+                "Baking bbbbbbbbbbb",
+                "Cooking aaaaaaaaaaa"
+            ]
+        );
+        assert_eq!(stats.episodes, 3);
+        assert_eq!(rss.channel.title, "All cooking");
+        assert_eq!(rss.channel.author, "All cooking");
+        assert_eq!(
+            rss.channel.image.image_url,
+            "https://cdn.example.com/cooking-1400x1400.jpg"
+        );
+
+        let missing = Feed {
+            channels: vec!["Missing".into()],
+            ..feed
+        };
+        let error = smol::block_on(process(
+            data_dir.path(),
+            "All cooking",
+            &missing,
+            &base_url,
+            &Settings::default(),
+            &Sources::default(),
+        ))
+        .unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.contains("Missing")));
+    }
+}
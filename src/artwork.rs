@@ -0,0 +1,990 @@
+//! Derive square channel and episode artwork from yt-dlp thumbnails.
+
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, GenericImageView, ImageDecoder, imageops};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::{Result, error::Error};
+
+/// How the source image fills the square artwork.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fill {
+    /// Fit the image inside the square and pad it with the background colour.
+    #[default]
+    Pad,
+    /// Fit the image inside the square, over a blurred and zoomed copy of itself.
+    Blur,
+    /// Scale the image to cover the square and keep its centre.
+    Crop,
+    /// Scale the image to cover the square and keep its most detailed region.
+    SmartCrop,
+}
+
+/// File format of the derived artwork.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// Same format as the source image.
+    #[default]
+    Source,
+    /// JPEG, with the configured quality.
+    Jpeg,
+    /// PNG.
+    Png,
+}
+
+/// An RGB colour, written `#rrggbb` in the configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub [u8; 3]);
+
+impl Color {
+    /// Black, the historical padding colour.
+    pub const BLACK: Color = Color([0, 0, 0]);
+}
+
+impl TryFrom<String> for Color {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let invalid = || Error::ConfigError(format!("invalid colour `{value}`, expected #rrggbb"));
+
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        let mut rgb = [0_u8; 3];
+        for (index, component) in rgb.iter_mut().enumerate() {
+            let digits = hex.get(index * 2..index * 2 + 2).ok_or_else(invalid)?;
+            *component = u8::from_str_radix(digits, 16).map_err(|_| invalid())?;
+        }
+        Ok(Color(rgb))
+    }
+}
+
+/// Options for one kind of derived artwork.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    /// Width and height of the square artwork, in pixels.
+    #[serde(deserialize_with = "deserialize_size")]
+    pub size: u32,
+    /// How the source image fills the square.
+    pub fill: Fill,
+    /// Padding colour, used by [`Fill::Pad`], and colour behind transparent pixels.
+    pub background: Color,
+    /// File format of the artwork.
+    pub format: Format,
+    /// JPEG quality, from 1 to 100.
+    pub quality: u8,
+    /// Maximum size of a JPEG artwork file, in bytes.
+    ///
+    /// The JPEG quality is lowered step by step until the file fits, down to
+    /// [`MIN_QUALITY`]. If it still does not fit, the smallest encoding is kept.
+    pub max_bytes: Option<u64>,
+}
+
+/// Lowest JPEG quality used to fit [`Options::max_bytes`].
+pub const MIN_QUALITY: u8 = 30;
+
+/// Step by which the JPEG quality is lowered to fit [`Options::max_bytes`].
+const QUALITY_STEP: u8 = 5;
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            size: 1400,
+            fill: Fill::Pad,
+            background: Color::BLACK,
+            format: Format::Source,
+            quality: 90,
+            max_bytes: None,
+        }
+    }
+}
+
+/// Deserialize the size of the artwork, which must be positive.
+fn deserialize_size<'de, D>(deserializer: D) -> std::result::Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let size = u32::deserialize(deserializer)?;
+    if size == 0 {
+        return Err(serde::de::Error::custom("artwork size must be positive"));
+    }
+    Ok(size)
+}
+
+/// Artwork options for channel and episode images.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Options for the channel artwork.
+    pub channel: Options,
+    /// Options for the episode artwork.
+    pub episode: Options,
+}
+
+/// Return the filepath of the artwork derived from `image_filepath`.
+///
+/// The filename encodes the options, so changing them produces a new file, e.g.
+/// `thumb-1400x1400.png` for the default options, `thumb-3000x3000-blur.jpg`, or
+/// `thumb-1400x1400-crop-bg-ffffff.png` with a background other than black. The extension
+/// is the one of the source image, unless [`Options::format`] selects another format; a
+/// source image without a filename, or without an extension to keep, is an error.
+pub fn derived_filepath<P: AsRef<Path>>(image_filepath: P, options: &Options) -> Result<PathBuf> {
+    let image_filepath = image_filepath.as_ref();
+    let invalid = |reason: &str| {
+        Error::ConfigError(format!(
+            "invalid artwork `{}`: {reason}",
+            image_filepath.display()
+        ))
+    };
+    let mut path = image_filepath.to_path_buf();
+    let filename = image_filepath
+        .file_stem()
+        .ok_or_else(|| invalid("no filename"))?
+        .to_string_lossy();
+    let extension = match options.format {
+        Format::Source => image_filepath
+            .extension()
+            .ok_or_else(|| invalid("no extension to keep the image format"))?,
+        Format::Jpeg => "jpg".as_ref(),
+        Format::Png => "png".as_ref(),
+    };
+    let size = options.size;
+    let fill = match options.fill {
+        Fill::Pad if options.background == Color::BLACK => String::new(),
+        Fill::Pad => {
+            let [r, g, b] = options.background.0;
+            format!("-pad-{r:02x}{g:02x}{b:02x}")
+        }
+        Fill::Blur => "-blur".into(),
+        Fill::Crop => "-crop".into(),
+        Fill::SmartCrop => "-smartcrop".into(),
+    };
+    // The background is also blended behind transparent pixels, whatever the fill.
+    let background = match options.fill {
+        Fill::Blur | Fill::Crop | Fill::SmartCrop if options.background != Color::BLACK => {
+            let [r, g, b] = options.background.0;
+            format!("-bg-{r:02x}{g:02x}{b:02x}")
+        }
+        _ => String::new(),
+    };
+    let filename = format!("{filename}-{size}x{size}{fill}{background}");
+    path.set_file_name(filename);
+    path.set_extension(extension);
+    Ok(path)
+}
+
+/// Whether [`derive_with_outcome`] created the derived artwork or reused an existing copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The artwork was derived from the source image.
+    Generated,
+    /// An up-to-date copy already existed.
+    Reused,
+}
+
+/// Derive the artwork for `image_filepath` unless an up-to-date copy already exists, and
+/// return its filepath.
+///
+/// The content hash of the source image, and the JPEG encoding options if any, are recorded
+/// next to the derived artwork, in a hidden `.<derived filename>.sha256` file. The artwork is
+/// derived again when the source is newer or the record differs, and when the derived file
+/// cannot be decoded or has the wrong dimensions.
+pub fn derive<P: AsRef<Path>>(image_filepath: P, options: &Options) -> Result<PathBuf> {
+    derive_with_outcome(image_filepath, options).map(|(derived_filepath, _)| derived_filepath)
+}
+
+/// Same as [`derive`], but also return whether the artwork was derived or reused.
+pub fn derive_with_outcome<P: AsRef<Path>>(
+    image_filepath: P,
+    options: &Options,
+) -> Result<(PathBuf, Outcome)> {
+    let derived_filepath = derived_filepath(image_filepath.as_ref(), options)?;
+    let record_filepath = record_filepath(&derived_filepath);
+    let record = record(image_filepath.as_ref(), &derived_filepath, options)?;
+
+    let outcome = if is_stale(
+        image_filepath.as_ref(),
+        &derived_filepath,
+        &record_filepath,
+        &record,
+        options,
+    )? {
+        debug!(artwork = %derived_filepath.display(), "deriving artwork");
+        resize_image_to_fill(image_filepath.as_ref(), derived_filepath.as_ref(), options)?;
+        Outcome::Generated
+    } else {
+        debug!(artwork = %derived_filepath.display(), "skipping artwork: up to date");
+        Outcome::Reused
+    };
+
+    // Also records the hash of derived files created before hashes were recorded.
+    if std::fs::read_to_string(&record_filepath).ok().as_deref() != Some(record.as_str()) {
+        std::fs::write(&record_filepath, &record)?;
+    }
+
+    Ok((derived_filepath, outcome))
+}
+
+/// Return `true` if the derived artwork must be created again.
+fn is_stale(
+    image_filepath: &Path,
+    derived_filepath: &Path,
+    record_filepath: &Path,
+    record: &str,
+    options: &Options,
+) -> Result<bool> {
+    let Ok(derived_metadata) = std::fs::metadata(derived_filepath) else {
+        return Ok(true);
+    };
+    if std::fs::metadata(image_filepath)?.modified()? > derived_metadata.modified()? {
+        return Ok(true);
+    }
+
+    // A missing record means the artwork predates hash records: keep it if it is valid.
+    if let Ok(recorded) = std::fs::read_to_string(record_filepath)
+        && recorded != record
+    {
+        return Ok(true);
+    }
+
+    match image::open(derived_filepath) {
+        Ok(derived) => Ok(derived.dimensions() != (options.size, options.size)),
+        Err(_) => Ok(true),
+    }
+}
+
+/// Return the filepath of the file recording the source hash of `derived_filepath`.
+fn record_filepath(derived_filepath: &Path) -> PathBuf {
+    let filename = derived_filepath.file_name().unwrap().to_string_lossy();
+    derived_filepath.with_file_name(format!(".{filename}.sha256"))
+}
+
+/// Return the record of the source hash and encoding options of `derived_filepath`.
+fn record(image_filepath: &Path, derived_filepath: &Path, options: &Options) -> Result<String> {
+    let source_hash = content_hash(image_filepath)?;
+    match image::ImageFormat::from_path(derived_filepath) {
+        Ok(image::ImageFormat::Jpeg) => {
+            let quality = options.quality;
+            let max_bytes = options
+                .max_bytes
+                .map_or_else(|| "none".into(), |max_bytes| max_bytes.to_string());
+            Ok(format!(
+                "{source_hash}\nquality = {quality}\nmax_bytes = {max_bytes}\n"
+            ))
+        }
+        _ => Ok(source_hash),
+    }
+}
+
+/// Return the hex-encoded SHA-256 hash of the file content.
+fn content_hash(filepath: &Path) -> Result<String> {
+    let content = std::fs::read(filepath)?;
+    let digest = Sha256::digest(&content);
+    Ok(digest.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Pad an image with the given color on all sides.
+fn pad_image(
+    img: &DynamicImage,
+    pad_left: u32,
+    pad_right: u32,
+    pad_top: u32,
+    pad_bottom: u32,
+    pad_color: image::Rgb<u8>,
+) -> image::RgbImage {
+    let (w, h) = img.dimensions();
+    let new_w = w + pad_left + pad_right;
+    let new_h = h + pad_top + pad_bottom;
+    let mut new_img = image::RgbImage::from_pixel(new_w, new_h, pad_color);
+    image::imageops::replace(
+        &mut new_img,
+        &img.to_rgb8(),
+        pad_left as i64,
+        pad_top as i64,
+    );
+    new_img
+}
+
+/// Resize an image to fill a square of `options.size` pixels.
+///
+/// With [`Fill::Pad`] and [`Fill::Blur`], the image is first scaled (aspect ratio preserved)
+/// to fit within a `size × size` box, then centred over the background. With [`Fill::Crop`]
+/// and [`Fill::SmartCrop`], the image is scaled to cover the box and the overflow is cropped.
+/// In every case the output is an exact `size × size` square.
+///
+/// This guarantees the square dimensions required by podcast clients such as
+/// Apple Podcasts, which reject episode artwork that is not exactly square
+/// (e.g. 1399×1400) and falls back to the show cover instead.
+///
+#[must_use = "Use the return value of this function"]
+fn resize_image_to_fill<P: AsRef<Path>>(
+    image_filepath: P,
+    resized_image_filepath: P,
+    options: &Options,
+) -> Result<()> {
+    let t = options.size;
+    if t == 0 {
+        return Err(Error::ConfigError("artwork size must be positive".into()));
+    }
+    let img = open_srgb(image_filepath.as_ref(), options.background)?;
+
+    let new_img = match options.fill {
+        Fill::Pad => {
+            let (resized, p_left, p_top) = fit(&img, t);
+            let (w, h) = resized.dimensions();
+            let p_right = (t - w) - p_left;
+            let p_bot = (t - h) - p_top;
+            let background = image::Rgb::from(options.background.0);
+            pad_image(&resized, p_left, p_right, p_top, p_bot, background)
+        }
+        Fill::Blur => {
+            let (resized, p_left, p_top) = fit(&img, t);
+            let mut background = img
+                .resize_to_fill(t, t, imageops::FilterType::Triangle)
+                .fast_blur(t as f32 / 25.0)
+                .to_rgb8();
+            imageops::replace(
+                &mut background,
+                &resized.to_rgb8(),
+                p_left as i64,
+                p_top as i64,
+            );
+            background
+        }
+        Fill::Crop => img
+            .resize_to_fill(t, t, imageops::FilterType::CatmullRom)
+            .to_rgb8(),
+        Fill::SmartCrop => smart_crop(&img, t).to_rgb8(),
+    };
+
+    let format = image::ImageFormat::from_path(resized_image_filepath.as_ref())?;
+    let content = encode(&new_img, format, options)?;
+    std::fs::write(resized_image_filepath, content)?;
+
+    Ok(())
+}
+
+/// Open an image as 8-bit sRGB pixels, without alpha channel.
+///
+/// Transparent pixels are blended over `background`, and pixels in an embedded RGB colour
+/// profile other than sRGB are converted to sRGB. Broken or non-RGB profiles are ignored.
+fn open_srgb(image_filepath: &Path, background: Color) -> Result<DynamicImage> {
+    let mut decoder = image::ImageReader::open(image_filepath)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let icc_profile = decoder.icc_profile()?;
+    let img = DynamicImage::from_decoder(decoder)?;
+
+    let mut rgb = if img.color().has_alpha() {
+        flatten_alpha(&img.to_rgba8(), background)
+    } else {
+        img.to_rgb8()
+    };
+
+    if let Some(icc_profile) = icc_profile {
+        to_srgb(&mut rgb, &icc_profile);
+    }
+
+    Ok(DynamicImage::ImageRgb8(rgb))
+}
+
+/// Blend the pixels of `img` over an opaque `background`.
+fn flatten_alpha(img: &image::RgbaImage, background: Color) -> image::RgbImage {
+    image::RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let [r, g, b, a] = img[(x, y)].0;
+        let blend = |value: u8, background: u8| {
+            ((value as u32 * a as u32 + background as u32 * (255 - a as u32) + 127) / 255) as u8
+        };
+        let [br, bg, bb] = background.0;
+        image::Rgb([blend(r, br), blend(g, bg), blend(b, bb)])
+    })
+}
+
+/// Convert the pixels of `img` from the RGB `icc_profile` to sRGB, in place.
+fn to_srgb(img: &mut image::RgbImage, icc_profile: &[u8]) {
+    let Ok(profile) = moxcms::ColorProfile::new_from_slice(icc_profile) else {
+        return;
+    };
+    if profile.color_space != moxcms::DataColorSpace::Rgb {
+        return;
+    }
+    let Ok(transform) = profile.create_transform_8bit(
+        moxcms::Layout::Rgb,
+        &moxcms::ColorProfile::new_srgb(),
+        moxcms::Layout::Rgb,
+        moxcms::TransformOptions::default(),
+    ) else {
+        return;
+    };
+
+    let mut converted = vec![0_u8; img.len()];
+    if transform.transform(img.as_raw(), &mut converted).is_ok() {
+        img.copy_from_slice(&converted);
+    }
+}
+
+/// Encode `img` in `format`.
+///
+/// JPEG images use [`Options::quality`], lowered step by step to fit [`Options::max_bytes`].
+fn encode(img: &image::RgbImage, format: image::ImageFormat, options: &Options) -> Result<Vec<u8>> {
+    if format != image::ImageFormat::Jpeg {
+        let mut content = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut content, format)?;
+        return Ok(content.into_inner());
+    }
+
+    let mut quality = options.quality.clamp(1, 100);
+    loop {
+        let mut content = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut content, quality)
+            .encode_image(img)?;
+
+        let fits = options
+            .max_bytes
+            .is_none_or(|max_bytes| content.len() as u64 <= max_bytes);
+        if fits || quality <= MIN_QUALITY {
+            return Ok(content);
+        }
+        quality = quality.saturating_sub(QUALITY_STEP).max(MIN_QUALITY);
+    }
+}
+
+/// Scale `img` to fit inside a `t × t` square, and return it along with the left and top
+/// offsets that centre it in the square.
+fn fit(img: &DynamicImage, t: u32) -> (DynamicImage, u32, u32) {
+    // Resize preserving the aspect ratio so the image fits inside a t×t box.
+    // The aspect-ratio-preserving `resize` may round the smaller axis down by
+    // one pixel, so the result is not guaranteed to be t wide/t tall.
+    let resized = img.resize(t, t, imageops::FilterType::CatmullRom);
+    let (w, h) = resized.dimensions();
+
+    // Pad both axes symmetrically to reach an exact t×t square. Deriving the
+    // padding from the actual resized dimensions (rather than from a
+    // precomputed target box) makes the square guarantee independent of how
+    // `resize` rounds each axis.
+    let p_left = (t - w) / 2;
+    let p_top = (t - h) / 2;
+
+    (resized, p_left, p_top)
+}
+
+/// Scale `img` to cover a `t × t` square, and crop it to the window with the most detail
+/// along its longer axis.
+///
+/// Detail is measured as the sum of absolute luminance differences between neighbouring
+/// pixels. Windows with equal detail are resolved towards the centre, so a featureless image
+/// is cropped like [`Fill::Crop`].
+fn smart_crop(img: &DynamicImage, t: u32) -> DynamicImage {
+    let (w, h) = img.dimensions();
+    let scale = t as f64 / w.min(h) as f64;
+    let scaled_w = ((w as f64 * scale).round() as u32).max(t);
+    let scaled_h = ((h as f64 * scale).round() as u32).max(t);
+    let scaled = img.resize_exact(scaled_w, scaled_h, imageops::FilterType::CatmullRom);
+
+    let horizontal = scaled_w > scaled_h;
+    let luma = scaled.to_luma8();
+    let length = if horizontal { scaled_w } else { scaled_h } as usize;
+
+    // Detail of each line (column or row) across the longer axis.
+    let mut detail = vec![0_u64; length];
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        let value = pixel[0] as i32;
+        let mut energy = 0;
+        if x + 1 < scaled_w {
+            energy += (luma[(x + 1, y)][0] as i32 - value).unsigned_abs();
+        }
+        if y + 1 < scaled_h {
+            energy += (luma[(x, y + 1)][0] as i32 - value).unsigned_abs();
+        }
+        let line = if horizontal { x } else { y } as usize;
+        detail[line] += energy as u64;
+    }
+
+    let window = t as usize;
+    let centre = (length - window) / 2;
+    let mut sum: u64 = detail[..window].iter().sum();
+    let mut best: (u64, usize) = (sum, 0);
+    for start in 1..=length - window {
+        sum = sum - detail[start - 1] + detail[start + window - 1];
+        let closer = start.abs_diff(centre) < best.1.abs_diff(centre);
+        if sum > best.0 || (sum == best.0 && closer) {
+            best = (sum, start);
+        }
+    }
+
+    let offset = best.1 as u32;
+    if horizontal {
+        scaled.crop_imm(offset, 0, t, t)
+    } else {
+        scaled.crop_imm(0, offset, t, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(size: u32, fill: Fill) -> Options {
+        Options {
+            size,
+            fill,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn derived_filepath_appends_dimensions() {
+        let path = PathBuf::from("/data/channel/image.jpg");
+        let resized = derived_filepath(&path, &Options::default()).unwrap();
+        assert_eq!(resized, PathBuf::from("/data/channel/image-1400x1400.jpg"));
+    }
+
+    #[test]
+    fn derived_filepath_preserves_extension() {
+        let png = PathBuf::from("/tmp/thumb.png");
+        let resized = derived_filepath(&png, &options(800, Fill::Pad)).unwrap();
+        assert_eq!(resized, PathBuf::from("/tmp/thumb-800x800.png"));
+    }
+
+    #[test]
+    fn derived_filepath_encodes_fill_and_background() {
+        let png = PathBuf::from("/tmp/thumb.png");
+        let padded = Options {
+            background: Color([0x20, 0xa0, 0xff]),
+            ..Options::default()
+        };
+        assert_eq!(
+            derived_filepath(&png, &padded).unwrap(),
+            PathBuf::from("/tmp/thumb-1400x1400-pad-20a0ff.png")
+        );
+        assert_eq!(
+            derived_filepath(&png, &options(3000, Fill::Blur)).unwrap(),
+            PathBuf::from("/tmp/thumb-3000x3000-blur.png")
+        );
+        assert_eq!(
+            derived_filepath(&png, &options(3000, Fill::SmartCrop)).unwrap(),
+            PathBuf::from("/tmp/thumb-3000x3000-smartcrop.png")
+        );
+        let cropped = Options {
+            background: Color([0xff, 0xff, 0xff]),
+            ..options(1400, Fill::Crop)
+        };
+        assert_eq!(
+            derived_filepath(&png, &cropped).unwrap(),
+            PathBuf::from("/tmp/thumb-1400x1400-crop-bg-ffffff.png")
+        );
+    }
+
+    #[test]
+    fn derived_filepath_rejects_images_without_extension() {
+        let extensionless = PathBuf::from("/data/cover");
+        let error = derived_filepath(&extensionless, &Options::default()).unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.contains("/data/cover")));
+
+        let jpeg = Options {
+            format: Format::Jpeg,
+            ..Options::default()
+        };
+        assert_eq!(
+            derived_filepath(&extensionless, &jpeg).unwrap(),
+            PathBuf::from("/data/cover-1400x1400.jpg")
+        );
+        assert!(derived_filepath(PathBuf::from("/"), &jpeg).is_err());
+    }
+
+    #[test]
+    fn zero_size_is_rejected_when_deserialized() {
+        let error = toml::from_str::<Options>("size = 0").unwrap_err();
+        assert!(error.to_string().contains("artwork size must be positive"));
+        assert_eq!(toml::from_str::<Options>("size = 3000").unwrap().size, 3000);
+    }
+
+    #[test]
+    fn color_parses_hex_triplets() {
+        assert_eq!(
+            Color::try_from("#0a0B0c".to_string()).unwrap(),
+            Color([10, 11, 12])
+        );
+        for invalid in ["0a0b0c", "#0a0b0", "#0a0b0g", "#ééé"] {
+            assert!(matches!(
+                Color::try_from(invalid.to_string()),
+                Err(Error::ConfigError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn pad_image_produces_correct_dimensions() {
+        let img = DynamicImage::new_rgb8(100, 60);
+        let padded = pad_image(&img, 10, 10, 20, 20, image::Rgb([0, 0, 0]));
+        assert_eq!(padded.width(), 120);
+        assert_eq!(padded.height(), 100);
+    }
+
+    #[test]
+    fn resize_image_to_fill_produces_exact_square_for_landscape() {
+        // A 1920×1080 source used to yield a 1399×1400 image (off-by-one) because
+        // the aspect-ratio-preserving resize rounded the width down to 1399 and
+        // only the height was padded. Apple Podcasts rejects such non-square
+        // episode artwork, so the output must be an exact square.
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        image::RgbImage::from_pixel(1920, 1080, image::Rgb([10, 20, 30]))
+            .save(&src)
+            .unwrap();
+        let out = tmp.path().join("thumb-1400x1400.png");
+
+        resize_image_to_fill(&src, &out, &Options::default()).unwrap();
+
+        let saved = image::open(&out).unwrap();
+        assert_eq!(saved.dimensions(), (1400, 1400));
+    }
+
+    #[test]
+    fn resize_image_to_fill_produces_exact_square_for_portrait() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        image::RgbImage::from_pixel(1080, 1920, image::Rgb([10, 20, 30]))
+            .save(&src)
+            .unwrap();
+        let out = tmp.path().join("thumb-1400x1400.png");
+
+        resize_image_to_fill(&src, &out, &Options::default()).unwrap();
+
+        let saved = image::open(&out).unwrap();
+        assert_eq!(saved.dimensions(), (1400, 1400));
+    }
+
+    #[test]
+    fn resize_image_to_fill_creates_square_output_for_every_fill() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("wide.png");
+        image::RgbImage::from_pixel(200, 100, image::Rgb([128, 128, 128]))
+            .save(&src)
+            .unwrap();
+
+        for fill in [Fill::Pad, Fill::Blur, Fill::Crop, Fill::SmartCrop] {
+            let dst = tmp.path().join("wide-100x100.png");
+            resize_image_to_fill(&src, &dst, &options(100, fill)).unwrap();
+
+            let resized = image::open(&dst).unwrap();
+            assert_eq!(resized.dimensions(), (100, 100), "{fill:?}");
+        }
+    }
+
+    #[test]
+    fn resize_image_to_fill_pads_with_background() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("portrait.png");
+        image::RgbImage::from_pixel(100, 200, image::Rgb([128, 128, 128]))
+            .save(&src)
+            .unwrap();
+        let padded = Options {
+            size: 100,
+            background: Color([255, 0, 0]),
+            ..Options::default()
+        };
+
+        let dst = tmp.path().join("portrait-100x100.png");
+        resize_image_to_fill(&src, &dst, &padded).unwrap();
+
+        let resized = image::open(&dst).unwrap().to_rgb8();
+        assert_eq!(resized[(0, 50)], image::Rgb([255, 0, 0]));
+        assert_eq!(resized[(50, 50)], image::Rgb([128, 128, 128]));
+    }
+
+    #[test]
+    fn resize_image_to_fill_blur_leaves_no_flat_bars() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("wide.png");
+        image::RgbImage::from_pixel(200, 100, image::Rgb([200, 100, 50]))
+            .save(&src)
+            .unwrap();
+
+        let dst = tmp.path().join("wide-100x100-blur.png");
+        resize_image_to_fill(&src, &dst, &options(100, Fill::Blur)).unwrap();
+
+        let resized = image::open(&dst).unwrap().to_rgb8();
+        assert_ne!(resized[(50, 0)], image::Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn smart_crop_keeps_the_detailed_region() {
+        // Flat grey everywhere, except a checkerboard in the right third.
+        let img = image::RgbImage::from_fn(300, 100, |x, y| {
+            if x >= 200 && (x + y) % 2 == 0 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([128, 128, 128])
+            }
+        });
+        let cropped = smart_crop(&DynamicImage::ImageRgb8(img), 100).to_rgb8();
+
+        assert_eq!(cropped.dimensions(), (100, 100));
+        let white = cropped
+            .pixels()
+            .filter(|p| **p == image::Rgb([255, 255, 255]))
+            .count();
+        assert!(white >= 4900, "{white} white pixels");
+    }
+
+    #[test]
+    fn smart_crop_centres_featureless_images() {
+        let img = image::RgbImage::from_fn(300, 100, |x, _| {
+            if (100..200).contains(&x) {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        });
+        // The only edges are at x=99 and x=199, equally covered by many windows.
+        let cropped = smart_crop(&DynamicImage::ImageRgb8(img), 100).to_rgb8();
+
+        assert_eq!(cropped[(50, 50)], image::Rgb([255, 255, 255]));
+    }
+
+    fn write_source(filepath: &Path, rgb: [u8; 3]) {
+        image::RgbImage::from_pixel(20, 10, image::Rgb(rgb))
+            .save(filepath)
+            .unwrap();
+    }
+
+    /// Set the modification time of `filepath` to `seconds` after the epoch.
+    fn set_modified(filepath: &Path, seconds: u64) {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+        std::fs::File::options()
+            .write(true)
+            .open(filepath)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn derive_reuses_up_to_date_artwork() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+
+        let derived = derive(&src, &options(10, Fill::Pad)).unwrap();
+        assert_eq!(derived, tmp.path().join("thumb-10x10.png"));
+        assert_eq!(image::open(&derived).unwrap().dimensions(), (10, 10));
+        assert!(tmp.path().join(".thumb-10x10.png.sha256").exists());
+
+        // Mark the derived file so that regeneration would be visible.
+        image::RgbImage::from_pixel(10, 10, image::Rgb([9, 9, 9]))
+            .save(&derived)
+            .unwrap();
+        let (_, outcome) = derive_with_outcome(&src, &options(10, Fill::Pad)).unwrap();
+        assert_eq!(outcome, Outcome::Reused);
+        assert_eq!(
+            image::open(&derived).unwrap().to_rgb8()[(0, 0)],
+            image::Rgb([9, 9, 9])
+        );
+    }
+
+    #[test]
+    fn derive_regenerates_when_source_changes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+        let derived = derive(&src, &options(10, Fill::Crop)).unwrap();
+
+        // Replace the source but keep it older than the derived file, so only the hash differs.
+        write_source(&src, [200, 100, 50]);
+        set_modified(&src, 1_000);
+        derive(&src, &options(10, Fill::Crop)).unwrap();
+
+        assert_eq!(
+            image::open(&derived).unwrap().to_rgb8()[(5, 5)],
+            image::Rgb([200, 100, 50])
+        );
+    }
+
+    #[test]
+    fn derive_regenerates_when_source_is_newer() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+        let derived = derive(&src, &options(10, Fill::Crop)).unwrap();
+
+        image::RgbImage::from_pixel(10, 10, image::Rgb([9, 9, 9]))
+            .save(&derived)
+            .unwrap();
+        set_modified(&derived, 1_000);
+        derive(&src, &options(10, Fill::Crop)).unwrap();
+
+        assert_eq!(
+            image::open(&derived).unwrap().to_rgb8()[(5, 5)],
+            image::Rgb([1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn derive_regenerates_invalid_artwork() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+        let derived = derive(&src, &options(10, Fill::Pad)).unwrap();
+
+        // Truncated file.
+        let content = std::fs::read(&derived).unwrap();
+        std::fs::write(&derived, &content[..content.len() / 2]).unwrap();
+        derive(&src, &options(10, Fill::Pad)).unwrap();
+        assert_eq!(image::open(&derived).unwrap().dimensions(), (10, 10));
+
+        // Wrong dimensions.
+        image::RgbImage::from_pixel(10, 9, image::Rgb([0, 0, 0]))
+            .save(&derived)
+            .unwrap();
+        derive(&src, &options(10, Fill::Pad)).unwrap();
+        assert_eq!(image::open(&derived).unwrap().dimensions(), (10, 10));
+    }
+
+    #[test]
+    fn derive_adopts_valid_artwork_without_record() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+        let derived = tmp.path().join("thumb-10x10.png");
+        image::RgbImage::from_pixel(10, 10, image::Rgb([9, 9, 9]))
+            .save(&derived)
+            .unwrap();
+        set_modified(&src, 1_000);
+
+        derive(&src, &options(10, Fill::Pad)).unwrap();
+
+        assert_eq!(
+            image::open(&derived).unwrap().to_rgb8()[(0, 0)],
+            image::Rgb([9, 9, 9])
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join(".thumb-10x10.png.sha256")).unwrap(),
+            content_hash(&src).unwrap()
+        );
+    }
+
+    #[test]
+    fn derived_filepath_uses_output_format_extension() {
+        let png = PathBuf::from("/tmp/thumb.png");
+        let jpeg = Options {
+            format: Format::Jpeg,
+            ..Options::default()
+        };
+        assert_eq!(
+            derived_filepath(&png, &jpeg).unwrap(),
+            PathBuf::from("/tmp/thumb-1400x1400.jpg")
+        );
+
+        let jpg = PathBuf::from("/tmp/cover.jpg");
+        let png_output = Options {
+            format: Format::Png,
+            ..Options::default()
+        };
+        assert_eq!(
+            derived_filepath(&jpg, &png_output).unwrap(),
+            PathBuf::from("/tmp/cover-1400x1400.png")
+        );
+    }
+
+    #[test]
+    fn derive_converts_png_source_to_jpeg() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+        let jpeg = Options {
+            size: 10,
+            format: Format::Jpeg,
+            ..Options::default()
+        };
+
+        let derived = derive(&src, &jpeg).unwrap();
+
+        assert_eq!(derived, tmp.path().join("thumb-10x10.jpg"));
+        let content = std::fs::read(&derived).unwrap();
+        assert_eq!(
+            image::guess_format(&content).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+    }
+
+    #[test]
+    fn derive_regenerates_when_quality_changes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("thumb.png");
+        write_source(&src, [1, 2, 3]);
+        let mut jpeg = Options {
+            size: 10,
+            format: Format::Jpeg,
+            ..Options::default()
+        };
+        let derived = derive(&src, &jpeg).unwrap();
+        std::fs::write(&derived, "stale").unwrap();
+        set_modified(&src, 1_000);
+
+        jpeg.quality = 80;
+        derive(&src, &jpeg).unwrap();
+
+        assert!(image::open(&derived).is_ok());
+    }
+
+    #[test]
+    fn encode_lowers_jpeg_quality_to_fit_budget() {
+        // Noise compresses badly, so the quality matters.
+        let img = image::RgbImage::from_fn(200, 200, |x, y| {
+            let v = (x.wrapping_mul(7919) ^ y.wrapping_mul(104_729)) as u8;
+            image::Rgb([v, v.wrapping_mul(3), v.wrapping_mul(5)])
+        });
+        let unbounded = Options {
+            format: Format::Jpeg,
+            quality: 95,
+            ..Options::default()
+        };
+        let full = encode(&img, image::ImageFormat::Jpeg, &unbounded).unwrap();
+
+        let budget = full.len() as u64 * 2 / 3;
+        let bounded = Options {
+            max_bytes: Some(budget),
+            ..unbounded.clone()
+        };
+        let fitted = encode(&img, image::ImageFormat::Jpeg, &bounded).unwrap();
+        assert!(fitted.len() as u64 <= budget);
+
+        let impossible = Options {
+            max_bytes: Some(1),
+            ..unbounded
+        };
+        let smallest = encode(&img, image::ImageFormat::Jpeg, &impossible).unwrap();
+        assert!(smallest.len() < fitted.len());
+    }
+
+    #[test]
+    fn open_srgb_blends_alpha_over_background() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("transparent.png");
+        image::RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                image::Rgba([0, 0, 0, 0])
+            } else {
+                image::Rgba([0, 0, 255, 255])
+            }
+        })
+        .save(&src)
+        .unwrap();
+
+        let img = open_srgb(&src, Color([255, 255, 255])).unwrap();
+
+        assert!(!img.color().has_alpha());
+        let rgb = img.to_rgb8();
+        assert_eq!(rgb[(0, 0)], image::Rgb([255, 255, 255]));
+        assert_eq!(rgb[(1, 0)], image::Rgb([0, 0, 255]));
+    }
+}
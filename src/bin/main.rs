@@ -1,21 +1,80 @@
-use std::ffi::{OsStr, OsString};
-use std::path::{Path, PathBuf};
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
+use tracing::{Level, info, warn};
+use tracing_subscriber::{
+    filter::Targets, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+};
+use url::Url;
 
 use podfeed::{
     Result,
-    config::{self, Config},
+    access::Generations,
+    config::{self, Config, Format, LogFormat},
+    generator::{Event, FeedGenerator},
+    report::Report,
+    settings::Settings,
+    writer::RssWriter,
 };
 
 fn main() -> Result<()> {
     let config = Config::parse();
+    init_logging(config.verbose, config.quiet, config.log_format);
 
     match config.command {
-        config::Command::Generate { data_dir, base_url } => {
-            let base_url = Path::new(&base_url);
-            smol::block_on(run(&data_dir, base_url))?;
+        config::Command::Generate {
+            data_dir,
+            base_url,
+            config,
+            report: report_filepath,
+            formats,
+        } => {
+            let report = Arc::new(Mutex::new(Report::new()));
+            let recorder = report.clone();
+            let mut builder = FeedGenerator::builder(data_dir, base_url)
+                .settings(load_settings(config.as_deref())?)
+                .on_event(move |event| {
+                    log_event(&event);
+                    recorder.lock().unwrap().record(&event);
+                });
+            for format in formats {
+                builder = match format {
+                    Format::Rss => builder.writer(RssWriter),
+                };
+            }
+            let generator = builder.build()?;
+            let result = smol::block_on(generator.generate());
+
+            if let Some(report_filepath) = report_filepath {
+                let mut report = report.lock().unwrap();
+                if let Err(e) = &result {
+                    report.fail(e);
+                }
+                report.finish();
+                report.save(report_filepath)?;
+            }
+            result?;
+        }
+        config::Command::Tokens {
+            data_dir,
+            base_url,
+            config,
+            rotate,
+        } => {
+            let base_url = podfeed::convert::parse_base_url(&base_url)?;
+            let settings = load_settings(config.as_deref())?;
+            smol::block_on(tokens(&data_dir, &base_url, &settings, rotate.as_deref()))?;
+        }
+        config::Command::Faststart { data_dir, dry_run } => {
+            smol::block_on(faststart(&data_dir, dry_run))?;
+        }
+        config::Command::Compact { data_dir, dry_run } => {
+            smol::block_on(compact(&data_dir, dry_run))?;
         }
         config::Command::GenerateCompletion { shell } => {
             let mut app = Config::command();
@@ -27,34 +86,187 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-async fn run(data_dir: &Path, base_url: &Path) -> Result<()> {
-    let directories = podfeed::convert::available_directories(data_dir).await?;
+fn load_settings(filepath: Option<&Path>) -> Result<Settings> {
+    match filepath {
+        Some(filepath) => Settings::load(filepath),
+        None => Ok(Settings::default()),
+    }
+}
+
+/// Log to stderr, at the info level unless `-v` or `-q` flags are given.
+fn init_logging(verbose: u8, quiet: u8, log_format: LogFormat) {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        ..=-2 => Level::ERROR,
+        -1 => Level::WARN,
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    // Dependencies only get to report warnings.
+    let filter = Targets::new()
+        .with_default(Level::WARN.min(level))
+        .with_target("podfeed", level);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_span_events(FmtSpan::CLOSE);
+
+    let registry = tracing_subscriber::registry().with(filter);
+    match log_format {
+        LogFormat::Text => registry.with(layer).init(),
+        LogFormat::Json => registry.with(layer.json()).init(),
+    }
+}
+
+/// Log the progress of the generation.
+fn log_event(event: &Event) {
+    match event {
+        Event::ChannelStarted { .. } | Event::ChannelFinished { .. } => {}
+        Event::Warning { channel, message } => warn!(channel, "{message}"),
+        Event::FeedGenerated {
+            channel,
+            subscriber,
+            split,
+            path,
+            stats,
+            ..
+        } => info!(
+            channel,
+            subscriber,
+            split,
+            path = path.as_ref().map(|path| path.display().to_string()),
+            episodes = stats.episodes,
+            "feed generated"
+        ),
+    }
+}
+
+/// Rewrite the MP4 episodes that are not faststart, printing their filepath.
+async fn faststart(data_dir: &Path, dry_run: bool) -> Result<()> {
+    let mut directories = podfeed::convert::available_directories(data_dir).await?;
+    directories.sort();
 
     for dirpath in &directories {
-        let rss_content = podfeed::convert::process(data_dir, dirpath, base_url).await?;
-        let rss_filepath = append_ext("xml", dirpath);
-        smol::fs::write(rss_filepath, rss_content).await?;
-        // let rss_filepath = data_dir.with_file_name()
-        // println!("{}", rendered_rss);
+        for episode_infofile in podfeed::info::episode::available_episodes(dirpath).await? {
+            let Some(media_filepath) = episode_infofile.media_filepath().await else {
+                continue;
+            };
+            let is_mp4 = media_filepath
+                .extension()
+                .is_some_and(|extension| extension == "mp4" || extension == "m4a");
+            if !is_mp4 {
+                continue;
+            }
+
+            let filepath = media_filepath.clone();
+            if !smol::unblock(move || podfeed::faststart::needs_rewrite(filepath)).await? {
+                continue;
+            }
+            println!("{}", media_filepath.display());
+            if !dry_run {
+                smol::unblock(move || podfeed::faststart::rewrite(media_filepath)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compact the info files of the episodes and channels, printing their filepath.
+async fn compact(data_dir: &Path, dry_run: bool) -> Result<()> {
+    let mut directories = podfeed::convert::available_directories(data_dir).await?;
+    directories.sort();
+
+    for dirpath in &directories {
+        let mut filepaths: Vec<_> = podfeed::info::episode::available_episodes(dirpath)
+            .await?
+            .into_iter()
+            .map(|infofile| infofile.filepath)
+            .collect();
+        filepaths.extend(
+            podfeed::info::channel::available_channels(dirpath)
+                .await?
+                .into_iter()
+                .map(|infofile| infofile.filepath),
+        );
+
+        for filepath in filepaths {
+            let path = filepath.clone();
+            if !smol::unblock(move || podfeed::info::compact::needs_compaction(path)).await? {
+                continue;
+            }
+            println!("{}", filepath.display());
+            if !dry_run {
+                smol::unblock(move || podfeed::info::compact::compact(filepath)).await?;
+            }
+        }
     }
     Ok(())
 }
 
-/// Returns a path with a new dotted extension component appended to the end.
-/// Note: does not check if the path is a file or directory; you should do that.
-/// # Example
-/// ```
-/// use pathext::append_ext;
-/// use std::path::PathBuf;
-/// let path = PathBuf::from("foo/bar/baz.txt");
-/// if !path.is_dir() {
-///    assert_eq!(append_ext("app", path), PathBuf::from("foo/bar/baz.txt.app"));
-/// }
-/// ```
-///
-fn append_ext(ext: impl AsRef<OsStr>, path: &Path) -> PathBuf {
-    let mut os_string: OsString = path.into();
-    os_string.push(".");
-    os_string.push(ext.as_ref());
-    os_string.into()
+/// Print the URL of each private feed, after rotating the token of `rotate` if requested.
+async fn tokens(
+    data_dir: &Path,
+    base_url: &Url,
+    settings: &Settings,
+    rotate: Option<&str>,
+) -> Result<()> {
+    let mut directories = podfeed::convert::available_directories(data_dir).await?;
+    directories.sort();
+    // Virtual feeds are named after a channel directory that does not exist.
+    let dirpaths: Vec<PathBuf> = directories
+        .into_iter()
+        .chain(
+            settings
+                .virtual_feeds()
+                .keys()
+                .map(|name| data_dir.join(name)),
+        )
+        .collect();
+    let channels: Vec<String> = dirpaths
+        .iter()
+        .map(|dirpath| dirpath.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+
+    let mut generations = Generations::load(data_dir)?;
+    if let Some(name) = rotate {
+        let names: Vec<&str> = channels
+            .iter()
+            .flat_map(|channel| settings.channel(channel).access.token_names(channel))
+            .collect();
+        generations.rotate(name, &names)?;
+        generations.save(data_dir)?;
+    }
+
+    for (dirpath, channel) in dirpaths.iter().zip(&channels) {
+        let channel_settings = settings.channel(channel);
+        let is_virtual = settings.virtual_feeds().contains_key(channel);
+
+        // Split feeds share the tokens of their channel; virtual feeds are not split.
+        let splits = if is_virtual {
+            &[][..]
+        } else {
+            &channel_settings.split[..]
+        };
+        let feed_dirpaths = std::iter::once(dirpath.clone()).chain(
+            splits
+                .iter()
+                .map(|split| dirpath.with_file_name(&split.name)),
+        );
+        for feed_dirpath in feed_dirpaths {
+            for feed in channel_settings.access.feeds(channel, &generations)? {
+                let Some(token) = &feed.token else {
+                    continue;
+                };
+                let feed_base_url = channel_settings.access.apply(base_url, token);
+                let feed_url = podfeed::convert::replace_base(
+                    data_dir,
+                    &feed_base_url,
+                    &feed.filepath(&feed_dirpath, "xml"),
+                )?;
+                let subscriber = feed.subscriber.as_deref().unwrap_or("-");
+                println!("{channel}\t{subscriber}\t{feed_url}");
+            }
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,379 @@
+//! Apple Podcasts categories of the feeds.
+//!
+//! Categories are listed by name, with subcategories after a slash, and validated against
+//! [`TAXONOMY`]. The first category is the main one, and subcategories of the same category
+//! are grouped under it:
+//!
+//! ```toml
+//! categories = ["Education/Courses", "Education/How To", "Technology"]
+//! ```
+
+use serde::Deserialize;
+
+use crate::rss;
+
+/// Categories and subcategories of Apple Podcasts.
+pub const TAXONOMY: &[(&str, &[&str])] = &[
+    (
+        "Arts",
+        &[
+            "Books",
+            "Design",
+            "Fashion & Beauty",
+            "Food",
+            "Performing Arts",
+            "Visual Arts",
+        ],
+    ),
+    (
+        "Business",
+        &[
+            "Careers",
+            "Entrepreneurship",
+            "Investing",
+            "Management",
+            "Marketing",
+            "Non-Profit",
+        ],
+    ),
+    ("Comedy", &["Comedy Interviews", "Improv", "Stand-Up"]),
+    (
+        "Education",
+        &["Courses", "How To", "Language Learning", "Self-Improvement"],
+    ),
+    ("Fiction", &["Comedy Fiction", "Drama", "Science Fiction"]),
+    ("Government", &[]),
+    ("History", &[]),
+    (
+        "Health & Fitness",
+        &[
+            "Alternative Health",
+            "Fitness",
+            "Medicine",
+            "Mental Health",
+            "Nutrition",
+            "Sexuality",
+        ],
+    ),
+    (
+        "Kids & Family",
+        &[
+            "Education for Kids",
+            "Parenting",
+            "Pets & Animals",
+            "Stories for Kids",
+        ],
+    ),
+    (
+        "Leisure",
+        &[
+            "Animation & Manga",
+            "Automotive",
+            "Aviation",
+            "Crafts",
+            "Games",
+            "Hobbies",
+            "Home & Garden",
+            "Video Games",
+        ],
+    ),
+    (
+        "Music",
+        &["Music Commentary", "Music History", "Music Interviews"],
+    ),
+    (
+        "News",
+        &[
+            "Business News",
+            "Daily News",
+            "Entertainment News",
+            "News Commentary",
+            "Politics",
+            "Sports News",
+            "Tech News",
+        ],
+    ),
+    (
+        "Religion & Spirituality",
+        &[
+            "Buddhism",
+            "Christianity",
+            "Hinduism",
+            "Islam",
+            "Judaism",
+            "Religion",
+            "Spirituality",
+        ],
+    ),
+    (
+        "Science",
+        &[
+            "Astronomy",
+            "Chemistry",
+            "Earth Sciences",
+            "Life Sciences",
+            "Mathematics",
+            "Natural Sciences",
+            "Nature",
+            "Physics",
+            "Social Sciences",
+        ],
+    ),
+    (
+        "Society & Culture",
+        &[
+            "Documentary",
+            "Personal Journals",
+            "Philosophy",
+            "Places & Travel",
+            "Relationships",
+        ],
+    ),
+    (
+        "Sports",
+        &[
+            "Baseball",
+            "Basketball",
+            "Cricket",
+            "Fantasy Sports",
+            "Football",
+            "Golf",
+            "Hockey",
+            "Rugby",
+            "Running",
+            "Soccer",
+            "Swimming",
+            "Tennis",
+            "Volleyball",
+            "Wilderness",
+            "Wrestling",
+        ],
+    ),
+    ("Technology", &[]),
+    ("True Crime", &[]),
+    (
+        "TV & Film",
+        &[
+            "After Shows",
+            "Film History",
+            "Film Interviews",
+            "Film Reviews",
+            "TV Reviews",
+        ],
+    ),
+];
+
+/// Category of the channels without a `categories` setting.
+pub const DEFAULT: &str = "Technology";
+
+/// A category with its subcategories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Category {
+    /// Name of the category, e.g. `Education`.
+    pub name: String,
+    /// Names of the subcategories, e.g. `Courses`.
+    pub subcategories: Vec<String>,
+}
+
+/// Validated categories of a channel, [`DEFAULT`] by default.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Categories(Vec<Category>);
+
+impl Default for Categories {
+    fn default() -> Self {
+        Self(vec![Category {
+            name: DEFAULT.into(),
+            subcategories: Vec::new(),
+        }])
+    }
+}
+
+impl TryFrom<Vec<String>> for Categories {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> std::result::Result<Self, String> {
+        if names.is_empty() {
+            return Err(format!(
+                "categories must not be empty, remove the setting for `{DEFAULT}`"
+            ));
+        }
+        let mut categories: Vec<Category> = Vec::new();
+        for name in &names {
+            let (name, subcategory) = match name.split_once('/') {
+                Some((name, subcategory)) => (name.trim(), Some(subcategory.trim())),
+                None => (name.trim(), None),
+            };
+            let (name, subcategories) = TAXONOMY
+                .iter()
+                .find(|(category, _)| *category == name)
+                .ok_or_else(|| unknown("category", name, TAXONOMY.iter().map(|(c, _)| *c)))?;
+
+            let index = match categories.iter().position(|c| c.name == *name) {
+                Some(index) => index,
+                None => {
+                    categories.push(Category {
+                        name: name.to_string(),
+                        subcategories: Vec::new(),
+                    });
+                    categories.len() - 1
+                }
+            };
+            if let Some(subcategory) = subcategory {
+                if subcategory.is_empty() {
+                    return Err(format!("empty subcategory of `{name}`"));
+                }
+                if !subcategories.contains(&subcategory) {
+                    let kind = format!("subcategory of `{name}`");
+                    return Err(unknown(&kind, subcategory, subcategories.iter().copied()));
+                }
+                let category = &mut categories[index];
+                if !category.subcategories.iter().any(|s| s == subcategory) {
+                    category.subcategories.push(subcategory.to_string());
+                }
+            }
+        }
+        Ok(Self(categories))
+    }
+}
+
+impl Categories {
+    /// Return the categories.
+    pub fn categories(&self) -> &[Category] {
+        &self.0
+    }
+
+    /// Return the `itunes:category` elements of the categories.
+    pub fn to_rss(&self) -> Vec<rss::channel::Category> {
+        self.0
+            .iter()
+            .map(|category| rss::channel::Category {
+                text: category.name.clone(),
+                subcategories: category
+                    .subcategories
+                    .iter()
+                    .map(|subcategory| rss::channel::Category {
+                        text: subcategory.clone(),
+                        subcategories: Vec::new(),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Return the error message of the unknown `name` of a `kind`, suggesting the closest of the
+/// `known` names.
+fn unknown<'a>(kind: &str, name: &str, known: impl Iterator<Item = &'a str>) -> String {
+    let lowercase = name.to_lowercase();
+    let mut suggestions: Vec<(usize, &str)> = known
+        .map(|candidate| (distance(&lowercase, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, candidate)| {
+            *distance <= (candidate.chars().count() / 3).max(2)
+                || candidate.to_lowercase().contains(&lowercase)
+                || lowercase.contains(&candidate.to_lowercase())
+        })
+        .collect();
+    suggestions.sort();
+    match suggestions.as_slice() {
+        [] => format!("unknown Apple {kind} `{name}`"),
+        suggestions => format!(
+            "unknown Apple {kind} `{name}`, did you mean {}?",
+            suggestions
+                .iter()
+                .take(3)
+                .map(|(_, candidate)| format!("`{candidate}`"))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        ),
+    }
+}
+
+/// Return the Levenshtein distance between `a` and `b`, in characters.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(names: &[&str]) -> std::result::Result<Categories, String> {
+        Categories::try_from(
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn categories_are_grouped_and_validated() {
+        let parsed = categories(&["Education/Courses", "Technology", "Education / How To"]);
+        assert_eq!(
+            parsed.unwrap().categories(),
+            [
+                Category {
+                    name: "Education".into(),
+                    subcategories: vec!["Courses".into(), "How To".into()],
+                },
+                Category {
+                    name: "Technology".into(),
+                    subcategories: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            Categories::default().categories()[0].name,
+            "Technology".to_string()
+        );
+
+        let typo = categories(&["Tecnology"]).unwrap_err();
+        assert_eq!(
+            typo,
+            "unknown Apple category `Tecnology`, did you mean `Technology`?"
+        );
+        let case = categories(&["Science/physics"]).unwrap_err();
+        assert!(case.contains("subcategory of `Science`"));
+        assert!(case.contains("did you mean `Physics`?"));
+        let unrelated = categories(&["Technology & Science"]).unwrap_err();
+        assert!(unrelated.ends_with("did you mean `Technology` or `Science`?"));
+        let nonsense = categories(&["Zzzzzzzz"]).unwrap_err();
+        assert_eq!(nonsense, "unknown Apple category `Zzzzzzzz`");
+    }
+
+    #[test]
+    fn empty_categories_are_rejected() {
+        assert!(categories(&[]).unwrap_err().contains("must not be empty"));
+        assert_eq!(
+            categories(&["Education/"]).unwrap_err(),
+            "empty subcategory of `Education`"
+        );
+        assert_eq!(
+            categories(&["Technology/ "]).unwrap_err(),
+            "empty subcategory of `Technology`"
+        );
+    }
+
+    #[test]
+    fn categories_render_nested_elements() {
+        let rss = categories(&["Society & Culture/Documentary"])
+            .unwrap()
+            .to_rss();
+        let xml = hard_xml::XmlWrite::to_string(&rss[0]).unwrap();
+        assert_eq!(
+            xml,
+            r#"<itunes:category text="Society &amp; Culture"><itunes:category text="Documentary"/></itunes:category>"#
+        );
+    }
+}
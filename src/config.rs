@@ -2,7 +2,7 @@
 
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand, ValueHint};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum, ValueHint};
 use clap_complete::Shell;
 
 /// Parse podfeed command-line arguments.
@@ -13,6 +13,34 @@ pub struct Config {
     /// Selection of commands.
     #[command(subcommand)]
     pub command: Command,
+
+    /// Log more details: `-v` for debug logs, `-vv` for trace logs.
+    #[arg(short = 'v', long = "verbose", action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Log less: `-q` for warnings and errors only, `-qq` for errors only.
+    #[arg(short = 'q', long = "quiet", action = ArgAction::Count, global = true)]
+    pub quiet: u8,
+
+    /// Format of the logs written to stderr.
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    pub log_format: LogFormat,
+}
+
+/// Format of the logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Format of the feed files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// RSS 2.0 with the iTunes podcast extensions, as `<name>.xml`.
+    Rss,
 }
 
 /// Select a podfeed command.
@@ -27,6 +55,59 @@ pub enum Command {
         /// Public URL corresponding to the data directory.
         #[arg(long = "base-url", env = "BASEURL")]
         base_url: String,
+
+        /// TOML configuration file with artwork options and per-channel overrides.
+        #[arg(short = 'c', long = "config", value_hint = ValueHint::FilePath, env = "PODFEED_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Write a JSON summary of the run to this file, even if the run fails.
+        #[arg(long = "report", value_hint = ValueHint::FilePath)]
+        report: Option<PathBuf>,
+
+        /// Format of the feed files; repeat to write each feed in several formats.
+        #[arg(long = "format", value_enum, default_values_t = [Format::Rss])]
+        formats: Vec<Format>,
+    },
+
+    /// Print the feed URLs of private channels, optionally rotating a token first.
+    Tokens {
+        /// Root directory containing channel directories created by yt-dlp.
+        #[arg(short = 'd', long = "data-dir", value_hint = ValueHint::DirPath, env = "DATADIR")]
+        data_dir: PathBuf,
+
+        /// Public URL corresponding to the data directory.
+        #[arg(long = "base-url", env = "BASEURL")]
+        base_url: String,
+
+        /// TOML configuration file with artwork options and per-channel overrides.
+        #[arg(short = 'c', long = "config", value_hint = ValueHint::FilePath, env = "PODFEED_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Rotate the token of this subscriber (or channel directory, for channel tokens).
+        #[arg(long = "rotate", value_name = "NAME")]
+        rotate: Option<String>,
+    },
+
+    /// Move the moov box of MP4 episodes in front of the media data, for streaming.
+    Faststart {
+        /// Root directory containing channel directories created by yt-dlp.
+        #[arg(short = 'd', long = "data-dir", value_hint = ValueHint::DirPath, env = "DATADIR")]
+        data_dir: PathBuf,
+
+        /// Only list the files that would be rewritten.
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+    },
+
+    /// Strip the bulky keys of the yt-dlp info files, keeping the originals as backups.
+    Compact {
+        /// Root directory containing channel directories created by yt-dlp.
+        #[arg(short = 'd', long = "data-dir", value_hint = ValueHint::DirPath, env = "DATADIR")]
+        data_dir: PathBuf,
+
+        /// Only list the files that would be rewritten.
+        #[arg(long = "dry-run")]
+        dry_run: bool,
     },
 
     /// Print a shell completion script to stdout.
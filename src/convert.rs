@@ -1,14 +1,25 @@
 //! Convert from info files to rss.
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::Write,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use futures::stream::StreamExt;
-use hard_xml::XmlWrite;
-use image::{DynamicImage, GenericImageView, imageops};
-
-use crate::{Result, info, rss};
-
-const TARGET_SIZE: u32 = 1400;
+use hard_xml::{XmlWrite, XmlWriter};
+use tracing::{Instrument, debug, debug_span, info};
+use url::Url;
+
+use crate::{
+    Result, artwork, category,
+    error::Error,
+    guid, itunes, rss,
+    settings::ChannelSettings,
+    source::{self, MetadataSource, Sources},
+    split,
+};
 
 /// List all playlist directories.
 pub async fn available_directories<P: AsRef<Path>>(data_dirpath: P) -> Result<Vec<PathBuf>> {
@@ -19,6 +30,7 @@ pub async fn available_directories<P: AsRef<Path>>(data_dirpath: P) -> Result<Ve
         let entry = entry?;
         let path = entry.path();
         if path.file_name().unwrap() == "Cache" {
+            debug!(dir = %path.display(), "skipping directory: yt-dlp cache");
             continue;
         }
         if smol::fs::metadata(&path).await?.is_dir() {
@@ -29,103 +41,410 @@ pub async fn available_directories<P: AsRef<Path>>(data_dirpath: P) -> Result<Ve
     Ok(directories)
 }
 
+/// Parse the public URL corresponding to the data directory.
+///
+/// The URL may contain a path prefix, a port and a query string, which are kept in the URLs
+/// built from it.
+pub fn parse_base_url(base_url: &str) -> Result<Url> {
+    let url = Url::parse(base_url)
+        .map_err(|e| Error::ConfigError(format!("invalid base url `{base_url}`: {e}")))?;
+    if url.cannot_be_a_base() {
+        return Err(Error::ConfigError(format!(
+            "invalid base url `{base_url}`: cannot contain paths"
+        )));
+    }
+    Ok(url)
+}
+
 /// Replace the parent base directory with the serving base url.
-fn replace_base<P: AsRef<Path>>(base_dir: P, base_url: P, filepath: P) -> String {
-    filepath
+///
+/// Each path component is percent-encoded and appended to the path of `base_url`. Files
+/// outside `base_dir`, such as artwork configured with an absolute path, cannot be served and
+/// are rejected.
+pub fn replace_base<P: AsRef<Path>>(base_dir: P, base_url: &Url, filepath: P) -> Result<String> {
+    let relative_path = filepath
         .as_ref()
-        .strip_prefix(base_dir)
-        .map(|s| base_url.as_ref().join(s))
-        .unwrap()
-        .to_string_lossy()
-        .to_string()
+        .strip_prefix(base_dir.as_ref())
+        .ok()
+        .filter(|path| {
+            path.components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        })
+        .ok_or_else(|| {
+            Error::ConfigError(format!(
+                "`{}` is outside the data directory `{}` and cannot be served",
+                filepath.as_ref().display(),
+                base_dir.as_ref().display()
+            ))
+        })?;
+
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .map_err(|_| Error::ConfigError(format!("invalid base url `{base_url}`")))?
+        .pop_if_empty()
+        .extend(
+            relative_path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy()),
+        );
+
+    Ok(url.into())
 }
 
-/// Parse channel & episodes, and return the rendered xml.
-pub async fn process<P: AsRef<Path>>(base_dir: P, dirpath: P, base_url: P) -> Result<String> {
-    let episode_infofiles = info::episode::available_episodes(dirpath.as_ref()).await?;
-    println!(
-        "- {} ({} episodes)",
-        dirpath.as_ref().to_string_lossy(),
-        episode_infofiles.len()
-    );
-
-    let mut episodes_with_indexes: Vec<(rss::episode::Episode, u32)> = vec![];
-    for episode_infofile in episode_infofiles {
-        let (episode_info, episode_enclosure, episode_image_filepath) =
-            episode_infofile.parse().await?;
-
-        let (rss_episode, playlist_index) = convert_episode(
-            base_dir.as_ref(),
-            base_url.as_ref(),
-            &episode_info,
-            &episode_enclosure,
-            &episode_image_filepath,
-        )?;
-        episodes_with_indexes.push((rss_episode, playlist_index));
+/// Figures about the episodes of a channel feed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of episodes.
+    pub episodes: usize,
+    /// Total size of the media files, in bytes.
+    pub media_bytes: u64,
+    /// Total duration of the episodes, in seconds, for those with a known duration.
+    pub duration_seconds: u64,
+    /// Problems that did not prevent the generation of the feed.
+    pub warnings: Vec<String>,
+    /// Number of artwork files derived from their source image.
+    pub artwork_generated: usize,
+    /// Number of artwork files already up to date.
+    pub artwork_reused: usize,
+    /// Time spent in each phase of the generation.
+    pub timings: Timings,
+}
+
+/// Time spent in each phase of the generation of a feed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Listing the info files.
+    pub discovery: Duration,
+    /// Parsing the info files and probing the media files.
+    pub parsing: Duration,
+    /// Deriving the artwork.
+    pub images: Duration,
+    /// Rendering the feed to xml and writing it.
+    pub rendering: Duration,
+}
+
+impl Stats {
+    /// Add the figures of `other`, such as those of the parsing of the channel, to these.
+    pub(crate) fn merge(&mut self, other: Stats) {
+        self.episodes += other.episodes;
+        self.media_bytes += other.media_bytes;
+        self.duration_seconds += other.duration_seconds;
+        self.warnings.splice(0..0, other.warnings);
+        self.artwork_generated += other.artwork_generated;
+        self.artwork_reused += other.artwork_reused;
+        self.timings.discovery += other.timings.discovery;
+        self.timings.parsing += other.timings.parsing;
+        self.timings.images += other.timings.images;
+        self.timings.rendering += other.timings.rendering;
+    }
+
+    /// Derive the artwork of `image_filepath`, recording the time spent and the outcome.
+    pub(crate) fn derive_artwork(
+        &mut self,
+        image_filepath: &Path,
+        options: &artwork::Options,
+    ) -> Result<PathBuf> {
+        let start = Instant::now();
+        let (artwork_filepath, outcome) = artwork::derive_with_outcome(image_filepath, options)?;
+        self.timings.images += start.elapsed();
+        match outcome {
+            artwork::Outcome::Generated => self.artwork_generated += 1,
+            artwork::Outcome::Reused => self.artwork_reused += 1,
+        }
+        Ok(artwork_filepath)
+    }
+}
+
+/// Parse channel & episodes, and return the feed with figures about its episodes.
+///
+/// The metadata comes from the built-in source selected by `settings`. Episodes that belong to
+/// a split feed of the channel are left out, see [`process_split`].
+pub async fn process<P: AsRef<Path>>(
+    base_dir: P,
+    dirpath: P,
+    base_url: &Url,
+    settings: &ChannelSettings,
+) -> Result<(rss::Rss, Stats)> {
+    let sources = Sources::default();
+    let source = sources.get(settings)?;
+    process_split(source.as_ref(), base_dir, dirpath, base_url, settings, None).await
+}
+
+/// Parse channel & episodes from `source`, and return the feed `split` off the channel, or the
+/// main feed if `split` is `None`, with figures about its episodes.
+pub async fn process_split<P: AsRef<Path>>(
+    source: &dyn MetadataSource,
+    base_dir: P,
+    dirpath: P,
+    base_url: &Url,
+    settings: &ChannelSettings,
+    split: Option<&split::Split>,
+) -> Result<(rss::Rss, Stats)> {
+    let mut parsed = parse(source, base_dir.as_ref(), dirpath.as_ref(), settings).await?;
+    let parse_stats = std::mem::take(&mut parsed.stats);
+    let (rss, mut stats) = parsed.feed(base_dir.as_ref(), base_url, split)?;
+    stats.merge(parse_stats);
+    Ok((rss, stats))
+}
+
+/// A channel parsed once, from which the feeds of its splits and subscribers are derived: they
+/// only differ by their episodes and URLs.
+pub(crate) struct ParsedChannel<'a> {
+    /// The channel, whose link is the public URL of `dirpath` if unset.
+    pub(crate) channel: source::Channel,
+    /// The channel directory, if the feed is not virtual.
+    pub(crate) dirpath: Option<PathBuf>,
+    /// Derived artwork of the channel.
+    pub(crate) artwork_filepath: PathBuf,
+    /// Derived artwork of the split feeds with their own artwork, keyed by split name.
+    pub(crate) split_artwork_filepaths: BTreeMap<String, PathBuf>,
+    /// Episodes in the order of the feed, whatever their split.
+    pub(crate) episodes: Vec<ParsedEpisode<'a>>,
+    /// Settings of the feed.
+    pub(crate) settings: &'a ChannelSettings,
+    /// Figures about the parsing, such as warnings, artwork and timings, but no episodes.
+    pub(crate) stats: Stats,
+}
+
+/// An episode parsed once, before its conversion to RSS.
+pub(crate) struct ParsedEpisode<'a> {
+    pub(crate) episode: source::Episode,
+    pub(crate) enclosure: source::Enclosure,
+    /// Derived artwork of the episode.
+    pub(crate) artwork_filepath: PathBuf,
+    /// Settings of the channel of the episode, for its GUID and iTunes tags.
+    pub(crate) settings: &'a ChannelSettings,
+}
+
+/// Parse the channel directory `dirpath` and its episodes from `source`, with those of all its
+/// split feeds, and derive their artwork.
+pub(crate) async fn parse<'a>(
+    source: &dyn MetadataSource,
+    base_dir: &Path,
+    dirpath: &Path,
+    settings: &'a ChannelSettings,
+) -> Result<ParsedChannel<'a>> {
+    let mut stats = Stats::default();
+    let start = Instant::now();
+    let mut channel_file = source.discover_channel(dirpath).await?;
+    stats.timings.discovery += start.elapsed();
+    if let Some(channel_file) = &mut channel_file {
+        stats.warnings.append(&mut channel_file.warnings);
+    }
+
+    let mut episodes =
+        parse_episodes(source, dirpath, settings, &mut HashSet::new(), &mut stats).await?;
+
+    let start = Instant::now();
+    let source_episodes: Vec<_> = episodes.iter().map(|parsed| &parsed.episode).collect();
+    let (mut channel, mut channel_image_filepath) = source
+        .parse_channel(dirpath, channel_file.as_ref(), &source_episodes)
+        .await?;
+    stats.timings.parsing += start.elapsed();
+    settings
+        .metadata
+        .apply(base_dir, &mut channel, &mut channel_image_filepath);
+    settings.itunes.fill_language(&mut channel, source_episodes);
+
+    // Resize the channel images to a square, e.g. with the "1400x1400" suffix.
+    let artwork_filepath =
+        stats.derive_artwork(&channel_image_filepath, &settings.artwork.channel)?;
+    let mut split_artwork_filepaths = BTreeMap::new();
+    for split in &settings.split {
+        if let Some(artwork) = &split.artwork {
+            let split_artwork_filepath =
+                stats.derive_artwork(&base_dir.join(artwork), &settings.artwork.channel)?;
+            split_artwork_filepaths.insert(split.name.clone(), split_artwork_filepath);
+        }
     }
 
     // Sort episodes by playlist index.
-    let episodes = {
-        episodes_with_indexes.sort_by_key(|a| a.1);
-        episodes_with_indexes
-            .into_iter()
-            .map(|(episode, _)| episode)
-            .collect::<Vec<_>>()
-    };
+    episodes.sort_by_key(|parsed| parsed.episode.playlist_index);
 
-    let channel_infofile = info::channel::available_channel(&dirpath).await?;
-    let (channel_info, channel_image_filepath) = channel_infofile.parse().await?;
-    let rss_channel = convert_channel(
-        base_dir.as_ref(),
-        base_url.as_ref(),
-        &channel_info,
-        &channel_image_filepath,
+    Ok(ParsedChannel {
+        channel,
+        dirpath: Some(dirpath.to_path_buf()),
+        artwork_filepath,
+        split_artwork_filepaths,
         episodes,
-    )?;
+        settings,
+        stats,
+    })
+}
 
-    let feed = rss::Rss {
-        version: "2.0".into(),
-        namespace: "http://www.itunes.com/dtds/podcast-1.0.dtd".into(),
-        content_namespace: "http://purl.org/rss/1.0/modules/content/".into(),
-        channel: rss_channel,
-    };
-    let rendered_rss = feed.to_string()?;
-    let xml_prolog = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+impl ParsedChannel<'_> {
+    /// Return the feed `split` off the channel, or the main feed if `split` is `None`, served
+    /// at `base_url`, with figures about its episodes.
+    pub(crate) fn feed(
+        &self,
+        base_dir: &Path,
+        base_url: &Url,
+        split: Option<&split::Split>,
+    ) -> Result<(rss::Rss, Stats)> {
+        let mut stats = Stats::default();
+        let start = Instant::now();
+        let mut channel = self.channel.clone();
+        if channel.link.is_none()
+            && let Some(dirpath) = &self.dirpath
+        {
+            channel.link = Some(replace_base(base_dir, base_url, dirpath)?);
+        }
+        let mut artwork_filepath = &self.artwork_filepath;
+        if let Some(split) = split {
+            channel.title.push_str(&split.title_suffix);
+            if let Some(split_artwork_filepath) = self.split_artwork_filepaths.get(&split.name) {
+                artwork_filepath = split_artwork_filepath;
+            }
+        }
+
+        let mut episodes = Vec::new();
+        for parsed in &self.episodes {
+            if self.dirpath.is_some()
+                && split::select(&self.settings.split, &parsed.episode) != split
+            {
+                continue;
+            }
+            stats.episodes += 1;
+            stats.media_bytes += parsed.enclosure.video_filelength;
+            stats.duration_seconds += u64::from(
+                parsed
+                    .enclosure
+                    .duration_seconds
+                    .or(parsed.episode.duration_seconds)
+                    .unwrap_or_default(),
+            );
+            let (rss_episode, _) = convert_episode(
+                base_dir,
+                base_url,
+                &parsed.episode,
+                &parsed.enclosure,
+                &parsed.artwork_filepath,
+                &parsed.settings.guid,
+                &parsed.settings.itunes,
+            )?;
+            episodes.push(rss_episode);
+        }
 
-    let rendered_rss = format!("{xml_prolog}\n{rendered_rss}");
-    Ok(rendered_rss)
+        let rss_channel = convert_channel(
+            base_dir,
+            base_url,
+            &channel,
+            artwork_filepath,
+            &self.settings.categories,
+            episodes,
+        )?;
+        stats.timings.rendering += start.elapsed();
+        Ok((feed(rss_channel), stats))
+    }
+}
+
+/// Parse the episodes of the channel directory `dirpath` from `source`, skipping those not
+/// ready yet, those excluded by the filters, and those with an ID already in `ids`, and derive
+/// their artwork.
+///
+/// The IDs of the parsed episodes, e.g. their YouTube ID, are added to `ids`, and the warnings,
+/// artwork and timings to `stats`.
+pub(crate) async fn parse_episodes<'a>(
+    source: &dyn MetadataSource,
+    dirpath: &Path,
+    settings: &'a ChannelSettings,
+    ids: &mut HashSet<String>,
+    stats: &mut Stats,
+) -> Result<Vec<ParsedEpisode<'a>>> {
+    let start = Instant::now();
+    let episode_files = source.discover_episodes(dirpath).await?;
+    stats.timings.discovery += start.elapsed();
+
+    let mut episodes = vec![];
+    for mut episode_file in episode_files {
+        stats.warnings.append(&mut episode_file.warnings);
+        let span = debug_span!("episode", id = %episode_file.id);
+        async {
+            if ids.contains(&episode_file.id) {
+                debug!(
+                    file = %episode_file.filepath.display(),
+                    "skipping episode: ID already in the feed"
+                );
+                return Ok(());
+            }
+
+            let start = Instant::now();
+            let Some((episode, mut enclosure, episode_image_filepath)) =
+                source.parse_episode(&episode_file).await?
+            else {
+                return Ok(());
+            };
+            stats.timings.parsing += start.elapsed();
+            if let Some(reason) = settings.filter.exclusion(&episode) {
+                info!(
+                    file = %episode_file.filepath.display(),
+                    "skipping episode: {reason}"
+                );
+                return Ok(());
+            }
+            stats.warnings.append(&mut enclosure.warnings);
+            if enclosure.faststart == Some(false) {
+                stats.warnings.push(format!(
+                    "{} is not faststart, see `podfeed faststart`",
+                    enclosure.video_filepath.display()
+                ));
+            }
+
+            // Resize the episode image to a square, e.g. with the "1400x1400" suffix.
+            let artwork_filepath =
+                stats.derive_artwork(&episode_image_filepath, &settings.artwork.episode)?;
+            ids.insert(episode_file.id.clone());
+            episodes.push(ParsedEpisode {
+                episode,
+                enclosure,
+                artwork_filepath,
+                settings,
+            });
+            Ok::<_, Error>(())
+        }
+        .instrument(span)
+        .await?;
+    }
+
+    Ok(episodes)
+}
+
+/// Wrap `channel` into an RSS feed.
+pub(crate) fn feed(channel: rss::channel::Channel) -> rss::Rss {
+    rss::Rss {
+        version: rss::VERSION.into(),
+        namespace: rss::ITUNES_NAMESPACE.into(),
+        content_namespace: rss::CONTENT_NAMESPACE.into(),
+        channel,
+    }
 }
 
-/// Convert channel Info into a RSS Channel.
+/// Render the feed to xml, with an xml prolog.
+pub fn render(feed: &rss::Rss) -> Result<String> {
+    let mut writer = XmlWriter::new(Vec::new());
+    writeln!(writer.inner, "{}", rss::XML_PROLOG)?;
+    feed.to_writer(&mut writer)?;
+    Ok(String::from_utf8(writer.into_inner()).map_err(hard_xml::XmlError::from)?)
+}
+
+/// Convert a channel into a RSS Channel, with the derived artwork at `artwork_filepath` and
+/// the `categories`.
+///
+/// The channel link defaults to the base URL.
 pub fn convert_channel<P: AsRef<Path>>(
     base_dir: P,
-    base_url: P,
-    source: &info::channel::Info,
-    image_filepath: P,
+    base_url: &Url,
+    source: &source::Channel,
+    artwork_filepath: P,
+    categories: &category::Categories,
     episodes: Vec<rss::episode::Episode>,
 ) -> Result<rss::channel::Channel> {
-    // Resize channel image to fill 1400x1400 and add the "1400x1400" suffix.
-    let resized_image_filepath = get_resized_image_filepath(image_filepath.as_ref(), TARGET_SIZE);
-
-    // Resize the channel image if the resized image does not already exist.
-    if !std::fs::exists(&resized_image_filepath)? {
-        resize_image_to_fill(
-            image_filepath.as_ref(),
-            resized_image_filepath.as_ref(),
-            TARGET_SIZE,
-        )?;
-    }
-
     let channel = rss::channel::Channel {
         title: source.title.clone(),
         description: source.description.clone(),
-        link: source.link.clone(),
+        link: source.link.clone().unwrap_or_else(|| base_url.to_string()),
         image: rss::channel::Image {
-            image_url: replace_base(
-                base_dir.as_ref(),
-                base_url.as_ref(),
-                resized_image_filepath.as_ref(),
-            ),
+            image_url: replace_base(base_dir.as_ref(), base_url, artwork_filepath.as_ref())?,
         },
         author: source.author.clone(),
         language: source.language().to_string(),
@@ -134,7 +453,7 @@ pub fn convert_channel<P: AsRef<Path>>(
             source.last_build_date().format("%a, %d %b %Y %H:%M:%S %z")
         ),
         pub_date: format!("{}", source.pub_date().format("%a, %d %b %Y %H:%M:%S %z")),
-        category: source.category().to_string(),
+        categories: categories.to_rss(),
         generator: source.generator().to_string(),
         explicit_content: source.explicit_content().to_string(),
         channel_type: source.channel_type().to_string(),
@@ -144,140 +463,65 @@ pub fn convert_channel<P: AsRef<Path>>(
     Ok(channel)
 }
 
-/// Convert episode Info to a RSS Episode.
+/// Convert an episode to a RSS Episode, with the derived artwork at `artwork_filepath`, the
+/// GUID formatted according to `guid_settings`, and the iTunes tags of `itunes_settings`.
+///
+/// The episode link defaults to the public URL of the media file.
 pub fn convert_episode<P: AsRef<Path>>(
     base_dir: P,
-    base_url: P,
-    source: &info::episode::Info,
-    enclosure: &info::episode::Enclosure,
-    image_filepath: P,
+    base_url: &Url,
+    source: &source::Episode,
+    enclosure: &source::Enclosure,
+    artwork_filepath: P,
+    guid_settings: &guid::Settings,
+    itunes_settings: &itunes::Settings,
 ) -> Result<(rss::episode::Episode, u32)> {
-    // Resize episode image to fill 1400x1400 and add the "1400x1400" suffix.
-    let resized_image_filepath = get_resized_image_filepath(image_filepath.as_ref(), TARGET_SIZE);
-
-    // Resize the episode image if the resized image does not already exist.
-    if !std::fs::exists(&resized_image_filepath)? {
-        resize_image_to_fill(
-            image_filepath.as_ref(),
-            resized_image_filepath.as_ref(),
-            TARGET_SIZE,
-        )?;
-    }
-
-    let target = rss::episode::Episode {
-        guid: source.guid.clone(),
+    let file_url = replace_base(
+        base_dir.as_ref(),
+        base_url,
+        enclosure.video_filepath.as_ref(),
+    )?;
+    let link = source.link.clone().unwrap_or_else(|| file_url.clone());
+    let mut target = rss::episode::Episode {
+        guid: guid_settings.guid(&source.guid, &link)?,
         pub_date: source
             .pub_date()
             .format("%a, %d %b %Y %H:%M:%S %z")
             .to_string(),
         title: source.title.clone(),
-        link: source.link.clone(),
+        link,
         description: source.description.clone(),
         enclosure: rss::episode::Enclosure {
-            file_url: replace_base(
-                base_dir.as_ref(),
-                base_url.as_ref(),
-                enclosure.video_filepath.as_ref(),
-            ),
+            file_url,
             file_length: enclosure.video_filelength.to_string(),
             file_type: enclosure.video_filetype.clone(),
         },
         author: source.author.clone(),
         image: rss::episode::Image {
-            file_url: replace_base(
-                base_dir.as_ref(),
-                base_url.as_ref(),
-                resized_image_filepath.as_ref(),
-            ),
+            file_url: replace_base(base_dir.as_ref(), base_url, artwork_filepath.as_ref())?,
         },
-        duration: source.duration_seconds.to_string(),
+        // The probed duration of the file is more accurate than the yt-dlp metadata.
+        duration: enclosure
+            .duration_seconds
+            .or(source.duration_seconds)
+            .map(|duration| duration.to_string()),
         explicit_content: "false".into(),
+        itunes_title: None,
+        subtitle: None,
+        summary: None,
+        keywords: None,
     };
+    itunes_settings.apply(source, &mut target);
 
     let playlist_index = source.playlist_index;
 
     Ok((target, playlist_index))
 }
 
-/// Return the filepath to the resized image (same extension as the original image).
-fn get_resized_image_filepath<P: AsRef<Path>>(image_filepath: P, target_size: u32) -> PathBuf {
-    let mut path = image_filepath.as_ref().to_path_buf();
-    let filename = path.file_stem().unwrap().to_string_lossy();
-    let extension = image_filepath.as_ref().extension().unwrap();
-    let filename = format!("{filename}-{target_size}x{target_size}");
-    path.set_file_name(filename);
-    path.set_extension(extension);
-    path
-}
-
-/// Pad an image with the given color on all sides.
-fn pad_image(
-    img: &DynamicImage,
-    pad_left: u32,
-    pad_right: u32,
-    pad_top: u32,
-    pad_bottom: u32,
-    pad_color: image::Rgb<u8>,
-) -> image::RgbImage {
-    let (w, h) = img.dimensions();
-    let new_w = w + pad_left + pad_right;
-    let new_h = h + pad_top + pad_bottom;
-    let mut new_img = image::RgbImage::from_pixel(new_w, new_h, pad_color);
-    image::imageops::replace(
-        &mut new_img,
-        &img.to_rgb8(),
-        pad_left as i64,
-        pad_top as i64,
-    );
-    new_img
-}
-
-/// Resize an image to fill a square of `target_size` pixels.
-///
-/// The image is first scaled (aspect ratio preserved) to fit within a
-/// `target_size × target_size` box, then padded symmetrically with black bars
-/// so the output is always an exact `target_size × target_size` square.
-///
-/// This guarantees the square dimensions required by podcast clients such as
-/// Apple Podcasts, which reject episode artwork that is not exactly square
-/// (e.g. 1399×1400) and falls back to the show cover instead.
-///
-/// This function saves the resized image to `path-1400x1400.png` if the `target_size` is 1400.
-///
-#[must_use = "Use the return value of this function"]
-fn resize_image_to_fill<P: AsRef<Path>>(
-    image_filepath: P,
-    resized_image_filepath: P,
-    target_size: u32,
-) -> Result<()> {
-    let img = image::open(image_filepath)?;
-    let t = target_size;
-
-    // Resize preserving the aspect ratio so the image fits inside a t×t box.
-    // The aspect-ratio-preserving `resize` may round the smaller axis down by
-    // one pixel, so the result is not guaranteed to be t wide/t tall.
-    let resized = img.resize(t, t, imageops::FilterType::CatmullRom);
-    let (w, h) = resized.dimensions();
-
-    // Pad both axes symmetrically to reach an exact t×t square. Deriving the
-    // padding from the actual resized dimensions (rather than from a
-    // precomputed target box) makes the square guarantee independent of how
-    // `resize` rounds each axis.
-    let black = image::Rgb::from([0, 0, 0]);
-    let p_left = (t - w) / 2;
-    let p_right = (t - w) - p_left;
-    let p_top = (t - h) / 2;
-    let p_bot = (t - h) - p_top;
-
-    let new_img = pad_image(&resized, p_left, p_right, p_top, p_bot, black);
-
-    new_img.save(resized_image_filepath)?;
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
+    use image::GenericImageView;
+
     use super::*;
 
     #[test]
@@ -297,9 +541,10 @@ mod tests {
     fn replace_base_substitutes_directory_with_url() {
         let result = replace_base(
             Path::new("/data/podcasts"),
-            Path::new("https://cdn.example.com/podcasts"),
+            &parse_base_url("https://cdn.example.com/podcasts").unwrap(),
             Path::new("/data/podcasts/channel1/episode.mp4"),
-        );
+        )
+        .unwrap();
         assert_eq!(
             result,
             "https://cdn.example.com/podcasts/channel1/episode.mp4"
@@ -310,107 +555,123 @@ mod tests {
     fn replace_base_handles_trailing_slash() {
         let result = replace_base(
             Path::new("/data"),
-            Path::new("https://cdn.example.com"),
+            &parse_base_url("https://cdn.example.com/").unwrap(),
             Path::new("/data/dir/file.jpg"),
-        );
+        )
+        .unwrap();
         assert_eq!(result, "https://cdn.example.com/dir/file.jpg");
     }
 
     #[test]
-    fn get_resized_image_filepath_appends_dimensions() {
-        let path = PathBuf::from("/data/channel/image.jpg");
-        let resized = get_resized_image_filepath(&path, 1400);
-        assert_eq!(resized, PathBuf::from("/data/channel/image-1400x1400.jpg"));
-    }
-
-    #[test]
-    fn get_resized_image_filepath_preserves_extension() {
-        let png = PathBuf::from("/tmp/thumb.png");
-        let resized = get_resized_image_filepath(&png, 800);
-        assert_eq!(resized, PathBuf::from("/tmp/thumb-800x800.png"));
+    fn replace_base_percent_encodes_path_components() {
+        let result = replace_base(
+            Path::new("/data"),
+            &parse_base_url("https://cdn.example.com").unwrap(),
+            Path::new("/data/Q&A #1/20230101--abc? 100% café.mp4"),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            "https://cdn.example.com/Q&A%20%231/20230101--abc%3F%20100%25%20caf%C3%A9.mp4"
+        );
     }
 
     #[test]
-    fn pad_image_produces_correct_dimensions() {
-        let img = DynamicImage::new_rgb8(100, 60);
-        let padded = pad_image(&img, 10, 10, 20, 20, image::Rgb([0, 0, 0]));
-        assert_eq!(padded.width(), 120);
-        assert_eq!(padded.height(), 100);
+    fn replace_base_keeps_prefix_port_and_query() {
+        let result = replace_base(
+            Path::new("/data"),
+            &parse_base_url("http://cdn.example.com:8080/pod casts/?key=value").unwrap(),
+            Path::new("/data/dir/file.jpg"),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            "http://cdn.example.com:8080/pod%20casts/dir/file.jpg?key=value"
+        );
     }
 
     #[test]
-    fn resize_image_to_fill_produces_exact_square_for_landscape() {
-        // A 1920×1080 source used to yield a 1399×1400 image (off-by-one) because
-        // the aspect-ratio-preserving resize rounded the width down to 1399 and
-        // only the height was padded. Apple Podcasts rejects such non-square
-        // episode artwork, so the output must be an exact square.
-        let tmp = tempfile::TempDir::new().unwrap();
-        let src = tmp.path().join("thumb.png");
-        image::RgbImage::from_pixel(1920, 1080, image::Rgb([10, 20, 30]))
-            .save(&src)
-            .unwrap();
-        let out = tmp.path().join("thumb-1400x1400.png");
-
-        resize_image_to_fill(&src, &out, 1400).unwrap();
-
-        let saved = image::open(&out).unwrap();
-        assert_eq!(saved.dimensions(), (1400, 1400));
+    fn replace_base_rejects_files_outside_the_data_directory() {
+        let base_url = parse_base_url("https://cdn.example.com").unwrap();
+        for filepath in [
+            "/etc/cover.jpg",
+            "/data/../cover.jpg",
+            "/database/cover.jpg",
+        ] {
+            let error =
+                replace_base(Path::new("/data"), &base_url, Path::new(filepath)).unwrap_err();
+            assert!(matches!(error, Error::ConfigError(msg) if msg.contains(filepath)));
+        }
     }
 
     #[test]
-    fn resize_image_to_fill_produces_exact_square_for_portrait() {
-        let tmp = tempfile::TempDir::new().unwrap();
-        let src = tmp.path().join("thumb.png");
-        image::RgbImage::from_pixel(1080, 1920, image::Rgb([10, 20, 30]))
-            .save(&src)
-            .unwrap();
-        let out = tmp.path().join("thumb-1400x1400.png");
-
-        resize_image_to_fill(&src, &out, 1400).unwrap();
-
-        let saved = image::open(&out).unwrap();
-        assert_eq!(saved.dimensions(), (1400, 1400));
+    fn parse_base_url_rejects_invalid_urls() {
+        for base_url in [
+            "cdn.example.com/podcasts",
+            "mailto:podcasts@example.com",
+            "",
+        ] {
+            let error = parse_base_url(base_url).unwrap_err();
+            assert!(matches!(error, Error::ConfigError(msg) if msg.contains(base_url)));
+        }
     }
 
     #[test]
     fn convert_episode_builds_rss_episode() {
-        // Create a minimal 2x2 image on disk for the resize step.
         let tmp = tempfile::TempDir::new().unwrap();
-        let img_path = tmp.path().join("thumb.png");
-        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
-            .save(&img_path)
-            .unwrap();
 
-        let source = info::episode::Info {
+        let source = source::Episode {
             guid: "abc123".into(),
-            upload_date: "20230101".into(),
+            upload_date: chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
             playlist_index: 5,
             title: "Ep Title".into(),
-            link: "https://youtube.com/watch?v=abc123".into(),
+            link: Some("https://youtube.com/watch?v=abc123".into()),
             description: "desc".into(),
             author: "Author".into(),
-            duration_seconds: 600,
+            duration_seconds: Some(600),
+            live_status: None,
+            was_live: None,
+            availability: None,
+            keywords: vec!["rust".into(), "podcast".into()],
+            age_limit: Some(18),
+            language: None,
         };
 
-        let enclosure = info::episode::Enclosure {
+        let enclosure = source::Enclosure {
             video_filepath: tmp.path().join("video.mp4"),
             video_filelength: 123456,
-            video_filetype: "mp4".into(),
+            video_filetype: "video/mp4".into(),
+            duration_seconds: None,
+            faststart: None,
+            warnings: Vec::new(),
         };
 
         let base_dir: &Path = tmp.path();
-        let base_url: &Path = Path::new("https://cdn.example.com");
-        let img: &Path = img_path.as_path();
-        let (ep, idx) = convert_episode(base_dir, base_url, &source, &enclosure, img).unwrap();
+        let base_url = parse_base_url("https://cdn.example.com").unwrap();
+        let artwork_filepath = tmp.path().join("thumb-1400x1400.png");
+        let (ep, idx) = convert_episode(
+            base_dir,
+            &base_url,
+            &source,
+            &enclosure,
+            &artwork_filepath,
+            &guid::Settings::default(),
+            &itunes::Settings::default(),
+        )
+        .unwrap();
 
         assert_eq!(idx, 5);
-        assert_eq!(ep.guid, "abc123");
+        assert_eq!(ep.guid.value, "abc123");
+        assert_eq!(ep.guid.is_perma_link, Some(false));
         assert_eq!(ep.author, "Author");
-        assert_eq!(ep.duration, "600");
-        assert_eq!(ep.enclosure.file_type, "mp4");
+        assert_eq!(ep.duration.as_deref(), Some("600"));
+        assert_eq!(ep.enclosure.file_type, "video/mp4");
         assert_eq!(ep.enclosure.file_length, "123456");
         assert!(ep.enclosure.file_url.starts_with("https://cdn.example.com"));
         assert!(ep.image.file_url.contains("thumb-1400x1400.png"));
+        assert_eq!(ep.explicit_content, "true");
+        assert_eq!(ep.itunes_title.as_deref(), Some("Ep Title"));
+        assert_eq!(ep.keywords.as_deref(), Some("rust,podcast"));
     }
 
     #[test]
@@ -462,41 +723,134 @@ mod tests {
                 .unwrap();
         }
 
-        let feed = smol::block_on(process(
+        // Episodes still downloading have no media file yet, and are skipped.
+        std::fs::write(
+            channel_dir.join("20230103--ccccccccccc--Pending.info.json"),
+            "{}",
+        )
+        .unwrap();
+
+        let (feed, stats) = smol::block_on(process(
             tmp.path(),
             &channel_dir,
-            Path::new("https://cdn.example.com"),
+            &parse_base_url("https://cdn.example.com").unwrap(),
+            &ChannelSettings::default(),
         ))
         .unwrap();
+        assert_eq!(stats.episodes, 2);
+        assert_eq!(stats.media_bytes, 6);
+        assert_eq!(stats.duration_seconds, 120);
+        assert_eq!(stats.artwork_generated, 3);
+        assert_eq!(stats.artwork_reused, 0);
+
+        let channel_artwork_filepath = channel_dir.join(format!("{channel_stem}-1400x1400.jpg"));
+        let resized = image::open(channel_artwork_filepath).unwrap();
+        assert_eq!(resized.dimensions(), (1400, 1400));
+
+        let feed = render(&feed).unwrap();
 
         assert!(feed.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
         assert!(feed.contains("<title>Example Channel</title>"));
         assert!(feed.contains("https://cdn.example.com/channel/"));
-        let earlier = feed.find("<guid>bbbbbbbbbbb</guid>").unwrap();
-        let later = feed.find("<guid>aaaaaaaaaaa</guid>").unwrap();
+        let earlier = feed
+            .find(r#"<guid isPermaLink="false">bbbbbbbbbbb</guid>"#)
+            .unwrap();
+        let later = feed
+            .find(r#"<guid isPermaLink="false">aaaaaaaaaaa</guid>"#)
+            .unwrap();
         assert!(earlier < later);
     }
 
     #[test]
-    fn convert_channel_builds_rss_channel_and_resizes_image() {
+    fn process_describes_channels_without_info_file_with_their_episodes() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let image_filepath = tmp.path().join("channel.png");
-        image::RgbImage::from_pixel(100, 50, image::Rgb([128, 128, 128]))
-            .save(&image_filepath)
+        let channel_dir = tmp.path().join("Downloads");
+        std::fs::create_dir(&channel_dir).unwrap();
+
+        for (date, id) in [("20230102", "aaaaaaaaaaa"), ("20230101", "bbbbbbbbbbb")] {
+            let stem = format!("{date}--{id}--Episode");
+            std::fs::write(
+                channel_dir.join(format!("{stem}.info.json")),
+                serde_json::json!({
+                    "id": id,
+                    "upload_date": date,
+                    "playlist_index": 1,
+                    "title": "Episode",
+                    "webpage_url": format!("https://youtube.com/watch?v={id}"),
+                    "description": "Episode description",
+                    "channel": "Author",
+                    "channel_url": "https://youtube.com/channel/UCtest",
+                    "playlist_title": format!("Author - Videos {date}"),
+                    "duration": 60
+                })
+                .to_string(),
+            )
             .unwrap();
+            std::fs::write(channel_dir.join(format!("{stem}.mp4")), [0_u8; 3]).unwrap();
+            image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+                .save(channel_dir.join(format!("{stem}.png")))
+                .unwrap();
+        }
+
+        let base_url = parse_base_url("https://cdn.example.com").unwrap();
+        let (feed, _) = smol::block_on(process(
+            tmp.path(),
+            &channel_dir,
+            &base_url,
+            &ChannelSettings::default(),
+        ))
+        .unwrap();
+        assert_eq!(feed.channel.title, "Author - Videos 20230102");
+        assert_eq!(feed.channel.description, "Author - Videos 20230102");
+        assert_eq!(feed.channel.author, "Author");
+        assert_eq!(feed.channel.link, "https://youtube.com/channel/UCtest");
+        assert_eq!(
+            feed.channel.image.image_url,
+            "https://cdn.example.com/Downloads/20230102--aaaaaaaaaaa--Episode-1400x1400.png"
+        );
+
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+            .save(tmp.path().join("downloads.jpg"))
+            .unwrap();
+        let settings: ChannelSettings = toml::from_str(
+            r#"
+            [metadata]
+            title = "My downloads"
+            description = "Everything I downloaded."
+            artwork = "downloads.jpg"
+            "#,
+        )
+        .unwrap();
+        let (feed, _) =
+            smol::block_on(process(tmp.path(), &channel_dir, &base_url, &settings)).unwrap();
+        assert_eq!(feed.channel.title, "My downloads");
+        assert_eq!(feed.channel.description, "Everything I downloaded.");
+        assert_eq!(feed.channel.author, "Author");
+        assert_eq!(
+            feed.channel.image.image_url,
+            "https://cdn.example.com/downloads-1400x1400.jpg"
+        );
+    }
+
+    #[test]
+    fn convert_channel_builds_rss_channel() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let artwork_filepath = tmp.path().join("channel-1400x1400.png");
 
-        let source = info::channel::Info {
-            upload_date: "20230101".into(),
+        let source = source::Channel {
+            upload_date: chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
             title: "Channel title".into(),
             description: "Channel description".into(),
-            link: "https://youtube.com/playlist?list=PLtest-12345".into(),
+            link: Some("https://youtube.com/playlist?list=PLtest-12345".into()),
             author: "Author".into(),
+            language: None,
         };
         let channel = convert_channel(
             tmp.path(),
-            Path::new("https://cdn.example.com"),
+            &parse_base_url("https://cdn.example.com").unwrap(),
             &source,
-            &image_filepath,
+            &artwork_filepath,
+            &category::Categories::default(),
             Vec::new(),
         )
         .unwrap();
@@ -509,7 +863,7 @@ mod tests {
         );
         assert_eq!(channel.author, "Author");
         assert_eq!(channel.language, "en");
-        assert_eq!(channel.category, "Technology & Science");
+        assert_eq!(channel.categories[0].text, "Technology");
         assert_eq!(channel.generator, "ytdlp");
         assert_eq!(channel.explicit_content, "false");
         assert_eq!(channel.channel_type, "Serial");
@@ -519,38 +873,37 @@ mod tests {
             channel.image.image_url,
             "https://cdn.example.com/channel-1400x1400.png"
         );
-
-        let resized = image::open(tmp.path().join("channel-1400x1400.png")).unwrap();
-        assert_eq!(resized.dimensions(), (1400, 1400));
     }
 
     #[test]
-    fn resize_image_to_fill_creates_square_output_for_landscape_image() {
+    fn process_reads_local_audio_files() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let src = tmp.path().join("wide.png");
-        image::RgbImage::from_pixel(200, 100, image::Rgb([128, 128, 128]))
-            .save(&src)
-            .unwrap();
-
-        let dst = tmp.path().join("wide-100x100.png");
-        resize_image_to_fill(&src, &dst, 100).unwrap();
-
-        let resized = image::open(&dst).unwrap();
-        assert_eq!(resized.dimensions(), (100, 100));
-    }
-
-    #[test]
-    fn resize_image_to_fill_creates_square_output_for_portrait_image() {
-        let tmp = tempfile::TempDir::new().unwrap();
-        let src = tmp.path().join("portrait.png");
-        image::RgbImage::from_pixel(100, 200, image::Rgb([128, 128, 128]))
-            .save(&src)
+        let channel_dir = tmp.path().join("Audiobook");
+        std::fs::create_dir(&channel_dir).unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+            .save(channel_dir.join("folder.jpg"))
             .unwrap();
+        std::fs::write(channel_dir.join("02 Chapter two.mp3"), [0_u8; 4]).unwrap();
+        std::fs::write(channel_dir.join("01 Chapter one.mp3"), [0_u8; 4]).unwrap();
 
-        let dst = tmp.path().join("portrait-100x100.png");
-        resize_image_to_fill(&src, &dst, 100).unwrap();
-
-        let resized = image::open(&dst).unwrap();
-        assert_eq!(resized.dimensions(), (100, 100));
+        let settings = ChannelSettings {
+            source: Some("local".into()),
+            ..ChannelSettings::default()
+        };
+        let base_url = parse_base_url("https://cdn.example.com").unwrap();
+        let (feed, stats) =
+            smol::block_on(process(tmp.path(), &channel_dir, &base_url, &settings)).unwrap();
+
+        assert_eq!(stats.episodes, 2);
+        assert_eq!(feed.channel.title, "Audiobook");
+        let episodes = &feed.channel.episodes;
+        assert_eq!(episodes[0].title, "01 Chapter one");
+        assert_eq!(episodes[0].guid.value, "01 Chapter one");
+        assert_eq!(
+            episodes[0].enclosure.file_url,
+            "https://cdn.example.com/Audiobook/01%20Chapter%20one.mp3"
+        );
+        assert_eq!(episodes[0].link, episodes[0].enclosure.file_url);
+        assert!(episodes[1].image.file_url.contains("folder-1400x1400"));
     }
 }
@@ -21,10 +21,26 @@ pub enum Error {
     #[error("multiple channel info files: `{0}`")]
     MultipleChannelInfoFiles(PathBuf),
 
+    /// Episode info file without a media file.
+    #[error("missing media file for `{0}`")]
+    MissingMedia(PathBuf),
+
     /// Configuration error.
     #[error("unexpected configuration: `{0}`")]
     ConfigError(String),
 
+    /// Media file that cannot be probed.
+    #[error("cannot probe media file `{0}`: {1}")]
+    Probe(PathBuf, String),
+
+    /// Audio file whose tags cannot be read.
+    #[error("cannot read the tags of `{0}`: {1}")]
+    Tags(PathBuf, String),
+
+    /// Hook command that failed, with the reason.
+    #[error("hook `{0}` failed: {1}")]
+    Hook(String, String),
+
     /// Image conversion error.
     #[error("image conversion error: `{source}`")]
     Image {
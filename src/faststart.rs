@@ -0,0 +1,257 @@
+//! Move the `moov` box of MP4 files in front of the media data ("faststart").
+//!
+//! Podcast clients stream such files without downloading them first. The rewritten file keeps
+//! the order of all other boxes, and the chunk offsets (`stco`/`co64`) of the moved media data
+//! are shifted by the size of the `moov` box.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{
+    Result,
+    error::Error,
+    probe::mp4::{self, BoxHeader},
+};
+
+/// Container boxes on the path from `moov` to the chunk offset tables.
+const CONTAINERS: [&[u8; 4]; 4] = [b"trak", b"mdia", b"minf", b"stbl"];
+
+/// Return `true` if the MP4 file at `filepath` has its `moov` box after its media data.
+pub fn needs_rewrite<P: AsRef<Path>>(filepath: P) -> Result<bool> {
+    let mut reader = BufReader::new(File::open(filepath.as_ref())?);
+    let boxes = mp4::top_level_boxes(&mut reader).map_err(|e| with_path(e, filepath.as_ref()))?;
+    Ok(mp4::is_faststart(&boxes) == Some(false))
+}
+
+/// Rewrite the MP4 file at `filepath` with its `moov` box in front of the media data.
+///
+/// The new file is written next to the original and renamed over it once complete. Return
+/// `false` if the file was already faststart.
+pub fn rewrite<P: AsRef<Path>>(filepath: P) -> Result<bool> {
+    let filepath = filepath.as_ref();
+    let mut reader = BufReader::new(File::open(filepath)?);
+    let boxes = mp4::top_level_boxes(&mut reader).map_err(|e| with_path(e, filepath))?;
+
+    if mp4::is_faststart(&boxes) != Some(false) {
+        return Ok(false);
+    }
+    let moov = boxes.iter().find(|b| &b.box_type == b"moov").unwrap();
+    let mdat = boxes.iter().find(|b| &b.box_type == b"mdat").unwrap();
+
+    // Everything from the first `mdat` up to the `moov` box moves forward by its size.
+    let mut payload = mp4::read_moov(&mut reader, moov).map_err(|e| with_path(e, filepath))?;
+    let shifted = mdat.offset..moov.offset;
+    shift_chunk_offsets(filepath, &mut payload, &shifted, moov.size)?;
+
+    let directory = filepath.parent().unwrap_or(Path::new("."));
+    let mut output = tempfile::NamedTempFile::new_in(directory)?;
+    {
+        let mut writer = BufWriter::new(output.as_file_mut());
+
+        copy_range(&mut reader, &mut writer, 0, mdat.offset)?;
+        write_moov_header(&mut writer, moov)?;
+        writer.write_all(&payload)?;
+        for b in boxes
+            .iter()
+            .filter(|b| b.offset >= mdat.offset && b != &moov)
+        {
+            copy_range(&mut reader, &mut writer, b.offset, b.size)?;
+        }
+        writer.flush()?;
+    }
+
+    let permissions = std::fs::metadata(filepath)?.permissions();
+    output.as_file().set_permissions(permissions)?;
+    output.as_file().sync_all()?;
+    output.persist(filepath).map_err(|e| e.error)?;
+
+    Ok(true)
+}
+
+/// Copy `size` bytes at `offset` of `reader` to `writer`.
+fn copy_range<R: Read + Seek, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    offset: u64,
+    size: u64,
+) -> Result<()> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let copied = io::copy(&mut reader.take(size), writer)?;
+    if copied != size {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
+/// Write the header of the `moov` box, with its original size encoding.
+fn write_moov_header<W: Write>(writer: &mut W, moov: &BoxHeader) -> Result<()> {
+    if moov.header_size == 16 {
+        writer.write_all(&1_u32.to_be_bytes())?;
+        writer.write_all(&moov.box_type)?;
+        writer.write_all(&moov.size.to_be_bytes())?;
+    } else {
+        writer.write_all(&(moov.size as u32).to_be_bytes())?;
+        writer.write_all(&moov.box_type)?;
+    }
+    Ok(())
+}
+
+/// Add `shift` to the chunk offsets within `shifted`, in all `stco` and `co64` boxes found
+/// in `payload`, the `moov` box of the MP4 file at `filepath`.
+///
+/// `stco` offsets that would exceed 32 bits are an error: the file is left untouched rather
+/// than upgraded to `co64`, which would change the size of the `moov` box and so the shift.
+fn shift_chunk_offsets(
+    filepath: &Path,
+    payload: &mut [u8],
+    shifted: &std::ops::Range<u64>,
+    shift: u64,
+) -> Result<()> {
+    let children = mp4::child_ranges(payload).map_err(|e| with_path(e, filepath))?;
+    for (box_type, range) in children {
+        let child = &mut payload[range];
+        match &box_type {
+            t if CONTAINERS.contains(&t) => shift_chunk_offsets(filepath, child, shifted, shift)?,
+            b"stco" => {
+                for entry in entries(filepath, child, 4)? {
+                    let offset = u32::from_be_bytes(entry.try_into().unwrap()) as u64;
+                    if shifted.contains(&offset) {
+                        let offset = u32::try_from(offset + shift).map_err(|_| {
+                            Error::Probe(
+                                filepath.into(),
+                                "32-bit chunk offsets (stco) would overflow after moving the moov \
+                                 box, the file is left untouched"
+                                    .into(),
+                            )
+                        })?;
+                        entry.copy_from_slice(&offset.to_be_bytes());
+                    }
+                }
+            }
+            b"co64" => {
+                for entry in entries(filepath, child, 8)? {
+                    let offset = u64::from_be_bytes(entry.try_into().unwrap());
+                    if shifted.contains(&offset) {
+                        entry.copy_from_slice(&(offset + shift).to_be_bytes());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Return the entries of a chunk offset table of the MP4 file at `filepath`, each `size` bytes
+/// long.
+fn entries<'a>(
+    filepath: &Path,
+    payload: &'a mut [u8],
+    size: usize,
+) -> Result<std::slice::ChunksExactMut<'a, u8>> {
+    let truncated = || Error::Probe(filepath.into(), "truncated chunk offset table".into());
+    let count = payload
+        .get(4..8)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+        .ok_or_else(truncated)?;
+    let end = count.checked_mul(size).and_then(|len| len.checked_add(8));
+    let table = end
+        .and_then(|end| payload.get_mut(8..end))
+        .ok_or_else(truncated)?;
+    Ok(table.chunks_exact_mut(size))
+}
+
+/// Fill in the filepath of a probe error.
+fn with_path(e: Error, filepath: &Path) -> Error {
+    match e {
+        Error::Probe(_, reason) => Error::Probe(filepath.into(), reason),
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::mp4::tests::{mp4_box, mvhd, trak};
+
+    /// Return an `stco` table, wrapped in its `trak/mdia/minf/stbl` containers.
+    fn trak_with_offsets(offsets: &[u32]) -> Vec<u8> {
+        let mut stco = vec![0_u8; 4];
+        stco.extend((offsets.len() as u32).to_be_bytes());
+        for offset in offsets {
+            stco.extend(offset.to_be_bytes());
+        }
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stco", &stco));
+        let minf = mp4_box(b"minf", &stbl);
+        mp4_box(b"trak", &mp4_box(b"mdia", &minf))
+    }
+
+    #[test]
+    fn rewrite_moves_moov_and_fixes_chunk_offsets() {
+        let directory = tempfile::tempdir().unwrap();
+        let filepath = directory.path().join("episode.mp4");
+
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        let mdat = mp4_box(b"mdat", b"chunk-one|chunk-two");
+        let chunk_one = ftyp.len() as u32 + 8;
+        let chunk_two = chunk_one + 10;
+        let moov_payload = [
+            mvhd(1000, 1000),
+            trak(b"soun"),
+            trak_with_offsets(&[chunk_one, chunk_two]),
+        ]
+        .concat();
+        let moov = mp4_box(b"moov", &moov_payload);
+        std::fs::write(
+            &filepath,
+            [ftyp.clone(), mdat.clone(), moov.clone()].concat(),
+        )
+        .unwrap();
+
+        assert!(needs_rewrite(&filepath).unwrap());
+        assert!(rewrite(&filepath).unwrap());
+        assert!(!needs_rewrite(&filepath).unwrap());
+        assert!(!rewrite(&filepath).unwrap());
+
+        let content = std::fs::read(&filepath).unwrap();
+        assert_eq!(content.len(), ftyp.len() + mdat.len() + moov.len());
+        assert_eq!(&content[..ftyp.len()], ftyp.as_slice());
+
+        // The chunk offsets of the rewritten file point at the same data.
+        let table_end = ftyp.len() + moov.len();
+        let offsets: Vec<usize> = content[table_end - 8..table_end]
+            .chunks(4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+            .collect();
+        assert_eq!(&content[offsets[0]..offsets[0] + 9], b"chunk-one");
+        assert_eq!(&content[offsets[1]..offsets[1] + 9], b"chunk-two");
+    }
+
+    #[test]
+    fn rewrite_keeps_offsets_of_data_after_moov() {
+        let mut payload = trak_with_offsets(&[10, 500]);
+        shift_chunk_offsets(Path::new("episode.mp4"), &mut payload, &(0..100), 42).unwrap();
+
+        let len = payload.len();
+        assert_eq!(&payload[len - 8..len - 4], &52_u32.to_be_bytes());
+        assert_eq!(&payload[len - 4..], &500_u32.to_be_bytes());
+    }
+
+    #[test]
+    fn overflowing_stco_offsets_name_the_file_left_untouched() {
+        let filepath = Path::new("/data/channel/episode.mp4");
+        let mut payload = trak_with_offsets(&[u32::MAX - 8]);
+        let original = payload.clone();
+
+        let error = shift_chunk_offsets(filepath, &mut payload, &(0..u64::MAX), 16).unwrap_err();
+
+        assert!(matches!(
+            &error,
+            Error::Probe(path, reason) if path == filepath && reason.contains("left untouched")
+        ));
+        assert_eq!(payload, original);
+    }
+}
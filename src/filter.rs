@@ -0,0 +1,217 @@
+//! Rules selecting the episodes of a channel.
+//!
+//! A rule matches an episode when all of its conditions do. An episode is kept when it
+//! matches at least one `include` rule, if there are any, and no `exclude` rule:
+//!
+//! ```toml
+//! # Drop YouTube Shorts and 10-hour streams.
+//! [[channels."Cooking".filter.include]]
+//! min_duration = 61
+//! max_duration = 36000
+//!
+//! [[channels."Cooking".filter.exclude]]
+//! availability = ["subscriber_only"]
+//! ```
+//!
+//! Excluded episodes are left untouched on disk.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{Result, error::Error, source::Episode};
+
+/// A regular expression, compared by its source.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern(pub Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl TryFrom<String> for Pattern {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Regex::new(&value)
+            .map(Pattern)
+            .map_err(|e| Error::ConfigError(format!("invalid regex `{value}`: {e}")))
+    }
+}
+
+/// A date, written `YYYY-MM-DD` in the configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Date(pub NaiveDate);
+
+impl TryFrom<String> for Date {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+            .map(Date)
+            .map_err(|_| Error::ConfigError(format!("invalid date `{value}`, expected YYYY-MM-DD")))
+    }
+}
+
+/// Conditions on an episode, all of which must hold for the rule to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    /// Regex searched in the title.
+    pub title: Option<Pattern>,
+    /// Regex searched in the description.
+    pub description: Option<Pattern>,
+    /// Minimum duration, in seconds.
+    pub min_duration: Option<u32>,
+    /// Maximum duration, in seconds.
+    pub max_duration: Option<u32>,
+    /// Earliest upload date, inclusive.
+    pub uploaded_after: Option<Date>,
+    /// Latest upload date, inclusive.
+    pub uploaded_before: Option<Date>,
+    /// yt-dlp `live_status` values, e.g. `was_live` or `not_live`.
+    pub live_status: Vec<String>,
+    /// yt-dlp `was_live` flag.
+    pub was_live: Option<bool>,
+    /// yt-dlp `availability` values, e.g. `public` or `subscriber_only`.
+    pub availability: Vec<String>,
+}
+
+/// Episode filters of a channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Rules of which an episode must match one, when not empty.
+    pub include: Vec<Rule>,
+    /// Rules of which an episode must match none.
+    pub exclude: Vec<Rule>,
+}
+
+impl Settings {
+    /// Return why the episode described by `info` is excluded, or `None` if it is kept.
+    pub fn exclusion(&self, info: &Episode) -> Option<String> {
+        if !self.include.is_empty() && !self.include.iter().any(|rule| rule.matches(info)) {
+            return Some("matches no include rule".into());
+        }
+        self.exclude
+            .iter()
+            .position(|rule| rule.matches(info))
+            .map(|index| format!("matches exclude rule {}", index + 1))
+    }
+}
+
+impl Rule {
+    /// Return whether the episode described by `info` meets all the conditions. Conditions
+    /// on a field that the source did not report do not hold.
+    pub fn matches(&self, info: &Episode) -> bool {
+        self.title
+            .as_ref()
+            .is_none_or(|pattern| pattern.0.is_match(&info.title))
+            && self
+                .description
+                .as_ref()
+                .is_none_or(|pattern| pattern.0.is_match(&info.description))
+            && self
+                .min_duration
+                .is_none_or(|min| info.duration_seconds.is_some_and(|d| d >= min))
+            && self
+                .max_duration
+                .is_none_or(|max| info.duration_seconds.is_some_and(|d| d <= max))
+            && self
+                .uploaded_after
+                .is_none_or(|after| info.upload_date >= after.0)
+            && self
+                .uploaded_before
+                .is_none_or(|before| info.upload_date <= before.0)
+            && (self.live_status.is_empty()
+                || info
+                    .live_status
+                    .as_ref()
+                    .is_some_and(|status| self.live_status.contains(status)))
+            && self
+                .was_live
+                .is_none_or(|was_live| info.was_live == Some(was_live))
+            && (self.availability.is_empty()
+                || info
+                    .availability
+                    .as_ref()
+                    .is_some_and(|availability| self.availability.contains(availability)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(title: &str, duration_seconds: Option<u32>, availability: Option<&str>) -> Episode {
+        serde_json::from_value::<crate::info::episode::Info>(serde_json::json!({
+            "id": "dQw4w9WgXcQ",
+            "upload_date": "20230519",
+            "playlist_index": 1,
+            "title": title,
+            "webpage_url": "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "description": "A test episode",
+            "channel": "Author",
+            "duration": duration_seconds,
+            "availability": availability,
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap()
+    }
+
+    #[test]
+    fn include_and_exclude_rules_select_episodes() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [[include]]
+            min_duration = 61
+            max_duration = 36000
+
+            [[exclude]]
+            title = "(?i)trailer"
+
+            [[exclude]]
+            availability = ["subscriber_only"]
+            uploaded_after = "2023-01-01"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.exclusion(&info("Episode", Some(600), None)), None);
+        assert_eq!(
+            settings.exclusion(&info("Short", Some(30), None)).unwrap(),
+            "matches no include rule"
+        );
+        assert!(settings.exclusion(&info("Stream", None, None)).is_some());
+        assert_eq!(
+            settings
+                .exclusion(&info("Season TRAILER", Some(600), None))
+                .unwrap(),
+            "matches exclude rule 1"
+        );
+        assert_eq!(
+            settings
+                .exclusion(&info("Episode", Some(600), Some("subscriber_only")))
+                .unwrap(),
+            "matches exclude rule 2"
+        );
+        assert_eq!(
+            Settings::default().exclusion(&info("Short", None, None)),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(toml::from_str::<Settings>("[[include]]\ntitle = \"(\"").is_err());
+        assert!(toml::from_str::<Settings>("[[include]]\nuploaded_after = \"2023\"").is_err());
+        assert!(toml::from_str::<Settings>("[[include]]\nmin_length = 1").is_err());
+    }
+}
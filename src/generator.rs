@@ -0,0 +1,913 @@
+//! Feed generation for a whole data directory.
+//!
+//! [`FeedGenerator`] ties together the channel discovery, the conversion to RSS, the virtual
+//! feeds, the private feeds and the hooks, and reports its progress through an optional
+//! callback:
+//!
+//! ```no_run
+//! use podfeed::generator::{Event, FeedGenerator};
+//!
+//! # fn main() -> podfeed::Result<()> {
+//! let generator = FeedGenerator::builder("/srv/podcasts", "https://example.com/podcasts")
+//!     .on_event(|event| {
+//!         if let Event::Warning { message, .. } = event {
+//!             eprintln!("{message}");
+//!         }
+//!     })
+//!     .build()?;
+//! let feeds = smol::block_on(generator.generate())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use url::Url;
+
+use crate::{
+    Result,
+    access::Generations,
+    aggregate,
+    convert::{self, Stats},
+    error::Error,
+    history,
+    hooks::{self, FeedUpdate, RunSummary},
+    rss,
+    settings::Settings,
+    source::{MetadataSource, Sources},
+    writer::{FeedWriter, RssWriter},
+};
+
+/// Where the rendered feeds are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Output {
+    /// Next to each channel directory, e.g. `<data dir>/<channel>.xml`.
+    #[default]
+    Adjacent,
+    /// In the given directory, with the same filenames as [`Output::Adjacent`].
+    Directory(PathBuf),
+    /// Nowhere: feeds are only returned, and hooks do not run.
+    Memory,
+}
+
+/// Progress of a generation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A channel directory is about to be processed.
+    ChannelStarted {
+        /// Name of the channel directory.
+        channel: String,
+        /// Path of the channel directory.
+        dirpath: PathBuf,
+    },
+    /// Something worth reporting that does not stop the generation.
+    Warning {
+        /// Name of the channel directory.
+        channel: String,
+        /// Description of the problem.
+        message: String,
+    },
+    /// A feed has been generated, and written unless the output is [`Output::Memory`].
+    FeedGenerated {
+        /// Name of the channel directory.
+        channel: String,
+        /// Path of the channel directory.
+        dirpath: PathBuf,
+        /// Subscriber of the feed, for private feeds with subscribers.
+        subscriber: Option<String>,
+        /// Name of the split feed, for feeds split off the channel.
+        split: Option<String>,
+        /// Path of the primary feed file, written by the first writer, unless the output is
+        /// [`Output::Memory`].
+        path: Option<PathBuf>,
+        /// Figures about the episodes of the feed.
+        stats: Box<Stats>,
+        /// GUIDs of the episodes absent from the previously written feed.
+        added: Vec<String>,
+        /// GUIDs of the episodes of the previously written feed that are no longer in the feed.
+        removed: Vec<String>,
+    },
+    /// All the feeds of a channel directory have been generated.
+    ChannelFinished {
+        /// Name of the channel directory.
+        channel: String,
+    },
+}
+
+/// A generated feed.
+#[derive(Debug)]
+pub struct Feed {
+    /// Name of the channel directory.
+    pub channel: String,
+    /// Path of the channel directory.
+    pub dirpath: PathBuf,
+    /// Subscriber of the feed, for private feeds with subscribers.
+    pub subscriber: Option<String>,
+    /// Name of the split feed, for feeds split off the channel.
+    pub split: Option<String>,
+    /// Public URL of the feed.
+    pub url: String,
+    /// Paths of the feed written by each writer, the first one being the primary feed file,
+    /// unless the output is [`Output::Memory`].
+    pub paths: Vec<PathBuf>,
+    /// The feed, only kept if the output is [`Output::Memory`]: written feeds are dropped
+    /// once on disk, so that a generation holds a single channel in memory at a time.
+    pub rss: Option<rss::Rss>,
+    /// GUIDs of the episodes of the feed, in the order of the feed.
+    pub guids: Vec<String>,
+    /// Figures about the episodes of the feed.
+    pub stats: Stats,
+    /// GUIDs of the episodes absent from the previous version of the feed.
+    pub added: Vec<String>,
+    /// GUIDs of the episodes of the previous version of the feed that are no longer in it.
+    pub removed: Vec<String>,
+}
+
+type Callback = Arc<dyn Fn(Event) + Send + Sync>;
+type Writers = Vec<Arc<dyn FeedWriter>>;
+
+/// Builder of a [`FeedGenerator`].
+pub struct FeedGeneratorBuilder {
+    data_dir: PathBuf,
+    base_url: String,
+    settings: Settings,
+    sources: Sources,
+    writers: Writers,
+    output: Output,
+    on_event: Option<Callback>,
+}
+
+impl FeedGeneratorBuilder {
+    /// Set the artwork, access, and hook settings (defaults otherwise).
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Register the metadata source `source` as `name`, for the channels with
+    /// `source = "<name>"` in their settings. The `yt-dlp` and `local` sources are built in.
+    pub fn source(
+        mut self,
+        name: impl Into<String>,
+        source: impl MetadataSource + 'static,
+    ) -> Self {
+        self.sources.insert(name, source);
+        self
+    }
+
+    /// Write each feed with `writer` too, in the order of the calls. Without any writer, feeds
+    /// are written as RSS.
+    pub fn writer(mut self, writer: impl FeedWriter + 'static) -> Self {
+        self.writers.push(Arc::new(writer));
+        self
+    }
+
+    /// Set where the rendered feeds are written ([`Output::Adjacent`] by default).
+    pub fn output(mut self, output: Output) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Call `on_event` to report progress. To consume events elsewhere, send them on a
+    /// channel from the callback.
+    pub fn on_event<F>(mut self, on_event: F) -> Self
+    where
+        F: Fn(Event) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Arc::new(on_event));
+        self
+    }
+
+    /// Return the generator, after validating the base URL and the extensions of the writers.
+    pub fn build(mut self) -> Result<FeedGenerator> {
+        if self.writers.is_empty() {
+            self.writers.push(Arc::new(RssWriter));
+        }
+        for (index, writer) in self.writers.iter().enumerate() {
+            let extension = writer.extension();
+            if self.writers[..index]
+                .iter()
+                .any(|other| other.extension() == extension)
+            {
+                return Err(Error::ConfigError(format!(
+                    "several writers use the extension `{extension}`"
+                )));
+            }
+        }
+        Ok(FeedGenerator {
+            base_url: convert::parse_base_url(&self.base_url)?,
+            data_dir: self.data_dir,
+            settings: self.settings,
+            sources: self.sources,
+            writers: self.writers,
+            output: self.output,
+            on_event: self.on_event,
+        })
+    }
+}
+
+/// Generates the feeds of the channel directories of a data directory.
+pub struct FeedGenerator {
+    data_dir: PathBuf,
+    base_url: Url,
+    settings: Settings,
+    sources: Sources,
+    writers: Writers,
+    output: Output,
+    on_event: Option<Callback>,
+}
+
+impl FeedGenerator {
+    /// Return a builder for the data directory `data_dir`, served at `base_url`.
+    pub fn builder(
+        data_dir: impl Into<PathBuf>,
+        base_url: impl Into<String>,
+    ) -> FeedGeneratorBuilder {
+        FeedGeneratorBuilder {
+            data_dir: data_dir.into(),
+            base_url: base_url.into(),
+            settings: Settings::default(),
+            sources: Sources::default(),
+            writers: Vec::new(),
+            output: Output::default(),
+            on_event: None,
+        }
+    }
+
+    /// Generate the feeds of every channel directory and virtual feed, then run the run hooks.
+    pub async fn generate(&self) -> Result<Vec<Feed>> {
+        let mut directories = convert::available_directories(&self.data_dir).await?;
+        directories.sort();
+        self.check_feed_names(&directories)?;
+        let generations = Generations::load(&self.data_dir)?;
+
+        let mut feeds = Vec::new();
+        for dirpath in &directories {
+            feeds.extend(self.channel_feeds(dirpath, None, &generations).await?);
+        }
+        for (name, virtual_feed) in self.settings.virtual_feeds() {
+            let dirpath = self.data_dir.join(name);
+            feeds.extend(
+                self.channel_feeds(&dirpath, Some(virtual_feed), &generations)
+                    .await?,
+            );
+        }
+
+        if self.output != Output::Memory {
+            let summary = RunSummary {
+                data_dir: self.data_dir.clone(),
+                feeds: feeds
+                    .iter()
+                    .map(|feed| self.update(feed, &feed.paths[0]))
+                    .collect(),
+            };
+            summary.run_hooks(self.settings.run_hooks()).await?;
+        }
+        Ok(feeds)
+    }
+
+    /// Generate the feeds of the channel directory `dirpath`, the main feed and the split
+    /// feeds, one per subscriber for private feeds with subscribers, and run the channel hooks.
+    pub async fn generate_channel(&self, dirpath: &Path) -> Result<Vec<Feed>> {
+        let directories = convert::available_directories(&self.data_dir).await?;
+        self.check_feed_names(&directories)?;
+        let generations = Generations::load(&self.data_dir)?;
+        self.channel_feeds(dirpath, None, &generations).await
+    }
+
+    /// Check that the channel directories `directories`, their split feeds and the virtual
+    /// feeds all have different names, since their feeds are written next to each other as
+    /// `<name>.xml`.
+    fn check_feed_names(&self, directories: &[PathBuf]) -> Result<()> {
+        let mut owners: BTreeMap<String, String> = BTreeMap::new();
+        let mut claim = |name: &str, owner: String| match owners.get(name) {
+            Some(first) => Err(Error::ConfigError(format!(
+                "{owner}: the feed name `{name}` is already taken by {first}"
+            ))),
+            None => {
+                owners.insert(name.to_string(), owner);
+                Ok(())
+            }
+        };
+
+        let channels: Vec<String> = directories
+            .iter()
+            .map(|dirpath| dirpath.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        for channel in &channels {
+            claim(channel, format!("the channel directory `{channel}`"))?;
+        }
+        for channel in &channels {
+            for split in &self.settings.channel(channel).split {
+                claim(&split.name, format!("channels.{channel}.split"))?;
+            }
+        }
+        for name in self.settings.virtual_feeds().keys() {
+            claim(name, format!("virtual.{name}"))?;
+        }
+        Ok(())
+    }
+
+    /// Generate the feeds of the channel directory `dirpath`, or of `virtual_feed`, which is
+    /// named after `dirpath` although the directory does not exist.
+    #[tracing::instrument(name = "channel", skip_all, fields(dir = %dirpath.display()))]
+    async fn channel_feeds(
+        &self,
+        dirpath: &Path,
+        virtual_feed: Option<&aggregate::Feed>,
+        generations: &Generations,
+    ) -> Result<Vec<Feed>> {
+        let channel = dirpath.file_name().unwrap().to_string_lossy().to_string();
+        let channel_settings = self.settings.channel(&channel);
+        self.emit(Event::ChannelStarted {
+            channel: channel.clone(),
+            dirpath: dirpath.to_path_buf(),
+        });
+
+        // Split feeds take some episodes of the channel; virtual feeds are not split.
+        let splits = match virtual_feed {
+            Some(_) => &[][..],
+            None => &channel_settings.split[..],
+        };
+
+        // The channel is parsed once: its feeds only differ by their episodes and URLs, and the
+        // figures about the parsing are counted in the first feed.
+        let mut parsed = match virtual_feed {
+            None => {
+                convert::parse(
+                    self.sources.get(channel_settings)?.as_ref(),
+                    &self.data_dir,
+                    dirpath,
+                    channel_settings,
+                )
+                .await?
+            }
+            Some(virtual_feed) => {
+                aggregate::parse(
+                    &self.data_dir,
+                    &channel,
+                    virtual_feed,
+                    &self.settings,
+                    &self.sources,
+                )
+                .await?
+            }
+        };
+        let mut parse_stats = Some(std::mem::take(&mut parsed.stats));
+
+        let mut feeds = Vec::new();
+        for split in std::iter::once(None).chain(splits.iter().map(Some)) {
+            let feed_dirpath = match split {
+                Some(split) => dirpath.with_file_name(&split.name),
+                None => dirpath.to_path_buf(),
+            };
+            // Private channels have one feed per subscriber, each with its own token in the URLs.
+            for access_feed in channel_settings.access.feeds(&channel, generations)? {
+                let base_url = match &access_feed.token {
+                    Some(token) => channel_settings.access.apply(&self.base_url, token),
+                    None => self.base_url.clone(),
+                };
+                let (mut rss, mut stats) = parsed.feed(&self.data_dir, &base_url, split)?;
+                if let Some(parse_stats) = parse_stats.take() {
+                    stats.merge(parse_stats);
+                }
+
+                let adjacent_filepaths: Vec<PathBuf> = self
+                    .writers
+                    .iter()
+                    .map(|writer| access_feed.filepath(&feed_dirpath, writer.extension()))
+                    .collect();
+
+                // Keep the sticky fields of the episodes already published in the RSS feed.
+                let start = Instant::now();
+                let rss_filepath = access_feed.filepath(&feed_dirpath, RssWriter.extension());
+                let previous_filepath = self.output_filepath(&rss_filepath).unwrap_or(rss_filepath);
+                let previous = match history::load(&previous_filepath) {
+                    Ok(previous) => previous,
+                    Err(e) => {
+                        stats.warnings.push(format!(
+                            "cannot read the previous feed `{}`, its history is lost: {e}",
+                            previous_filepath.display()
+                        ));
+                        None
+                    }
+                };
+                stats.timings.parsing += start.elapsed();
+                channel_settings.history.apply(
+                    previous.as_ref().map(|previous| &previous.channel),
+                    &mut rss.channel,
+                );
+                let previous_guids: Vec<String> = previous
+                    .iter()
+                    .flat_map(|previous| &previous.channel.episodes)
+                    .map(|episode| episode.guid.value.clone())
+                    .collect();
+                let guids: Vec<String> = rss
+                    .channel
+                    .episodes
+                    .iter()
+                    .map(|episode| episode.guid.value.clone())
+                    .collect();
+                let (added, removed) = hooks::diff(&previous_guids, &guids);
+
+                for message in &stats.warnings {
+                    self.emit(Event::Warning {
+                        channel: channel.clone(),
+                        message: message.clone(),
+                    });
+                }
+
+                let url = convert::replace_base(&self.data_dir, &base_url, &adjacent_filepaths[0])?;
+                let mut feed = Feed {
+                    channel: channel.clone(),
+                    dirpath: dirpath.to_path_buf(),
+                    subscriber: access_feed.subscriber,
+                    split: split.map(|split| split.name.clone()),
+                    url,
+                    paths: Vec::new(),
+                    rss: None,
+                    guids,
+                    stats,
+                    added,
+                    removed,
+                };
+
+                // The first writer always writes the primary feed file.
+                let path = if self.output == Output::Memory {
+                    feed.rss = Some(rss);
+                    None
+                } else {
+                    feed = self.write(feed, rss, &adjacent_filepaths).await?;
+                    let path = feed.paths[0].clone();
+                    self.update(&feed, &path)
+                        .run_hooks(&channel_settings.hooks.channel)
+                        .await?;
+                    Some(path)
+                };
+
+                self.emit(Event::FeedGenerated {
+                    channel: feed.channel.clone(),
+                    dirpath: feed.dirpath.clone(),
+                    subscriber: feed.subscriber.clone(),
+                    split: feed.split.clone(),
+                    path,
+                    stats: Box::new(feed.stats.clone()),
+                    added: feed.added.clone(),
+                    removed: feed.removed.clone(),
+                });
+                feeds.push(feed);
+            }
+        }
+
+        self.emit(Event::ChannelFinished { channel });
+        Ok(feeds)
+    }
+
+    /// Return where the feed file at `adjacent_filepath`, next to its channel directory, is
+    /// written, or `None` if the output is [`Output::Memory`].
+    fn output_filepath(&self, adjacent_filepath: &Path) -> Option<PathBuf> {
+        match &self.output {
+            Output::Adjacent => Some(adjacent_filepath.to_path_buf()),
+            Output::Directory(output_dirpath) => {
+                Some(output_dirpath.join(adjacent_filepath.file_name().unwrap()))
+            }
+            Output::Memory => None,
+        }
+    }
+
+    /// Write `rss` with each writer, whose file next to the channel directory would be at
+    /// the corresponding path of `adjacent_filepaths`, and return `feed` with the written
+    /// paths. `rss` is dropped once written.
+    async fn write(
+        &self,
+        mut feed: Feed,
+        rss: rss::Rss,
+        adjacent_filepaths: &[PathBuf],
+    ) -> Result<Feed> {
+        let start = Instant::now();
+        let outputs: Vec<(Arc<dyn FeedWriter>, PathBuf)> = self
+            .writers
+            .iter()
+            .zip(adjacent_filepaths)
+            .filter_map(|(writer, adjacent_filepath)| {
+                let filepath = self.output_filepath(adjacent_filepath)?;
+                Some((writer.clone(), filepath))
+            })
+            .collect();
+        let mut feed = smol::unblock(move || {
+            for (writer, filepath) in outputs {
+                write_file(writer.as_ref(), &rss.channel, &filepath)?;
+                feed.paths.push(filepath);
+            }
+            Ok::<_, Error>(feed)
+        })
+        .await?;
+        feed.stats.timings.rendering += start.elapsed();
+        Ok(feed)
+    }
+
+    /// Return the update of the written `feed`, whose primary file is at `feed_path`.
+    fn update(&self, feed: &Feed, feed_path: &Path) -> FeedUpdate {
+        FeedUpdate {
+            channel: feed.channel.clone(),
+            channel_path: feed.dirpath.clone(),
+            feed_path: feed_path.to_path_buf(),
+            feed_url: feed.url.clone(),
+            subscriber: feed.subscriber.clone(),
+            added: feed.added.clone(),
+            removed: feed.removed.clone(),
+        }
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(on_event) = &self.on_event {
+            on_event(event);
+        }
+    }
+}
+
+/// Stream the feed file of `channel` written by `writer` into a partial file, which replaces
+/// the file at `filepath` once complete, or is removed on error.
+fn write_file(
+    writer: &dyn FeedWriter,
+    channel: &rss::channel::Channel,
+    filepath: &Path,
+) -> Result<()> {
+    let mut partial_filename = filepath.file_name().unwrap().to_os_string();
+    partial_filename.push(".partial");
+    let partial_filepath = filepath.with_file_name(partial_filename);
+    let written = File::create(&partial_filepath)
+        .map_err(Error::from)
+        .and_then(|file| {
+            let mut output = BufWriter::new(file);
+            writer.write(channel, &mut output)?;
+            output.flush()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(std::fs::rename(&partial_filepath, filepath)?));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&partial_filepath);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use futures::future::BoxFuture;
+
+    use super::*;
+    use crate::{
+        info,
+        source::{self, ChannelFile, EpisodeFile},
+    };
+
+    /// Create a channel directory with one episode in `data_dir`.
+    fn channel(data_dir: &Path, name: &str) {
+        let channel_dir = data_dir.join(name);
+        std::fs::create_dir(&channel_dir).unwrap();
+
+        let channel_stem = "NA--PLtest-123456--Example_Playlist--NA";
+        std::fs::write(
+            channel_dir.join(format!("{channel_stem}.info.json")),
+            r#"{
+                "id": "PLtest-12345",
+                "title": "Example Channel",
+                "description": "Channel description",
+                "webpage_url": "https://youtube.com/playlist?list=PLtest-12345",
+                "channel": "Author",
+                "modified_date": "20230101"
+            }"#,
+        )
+        .unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+            .save(channel_dir.join(format!("{channel_stem}.jpg")))
+            .unwrap();
+
+        let stem = "20230101--aaaaaaaaaaa--Episode";
+        std::fs::write(
+            channel_dir.join(format!("{stem}.info.json")),
+            r#"{
+                "id": "aaaaaaaaaaa",
+                "upload_date": "20230101",
+                "playlist_index": 1,
+                "title": "Episode",
+                "webpage_url": "https://youtube.com/watch?v=aaaaaaaaaaa",
+                "description": "Episode description",
+                "channel": "Author",
+                "duration": 60
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(channel_dir.join(format!("{stem}.mp4")), [0_u8; 3]).unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+            .save(channel_dir.join(format!("{stem}.png")))
+            .unwrap();
+    }
+
+    #[test]
+    fn generate_writes_feeds_and_reports_events() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "channel");
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let recorder = events.clone();
+        let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .on_event(move |event| recorder.lock().unwrap().push(event))
+            .build()
+            .unwrap();
+        let feeds = smol::block_on(generator.generate()).unwrap();
+
+        assert_eq!(feeds.len(), 1);
+        let feed = &feeds[0];
+        assert!(feed.rss.is_none());
+        assert_eq!(feed.guids, vec!["aaaaaaaaaaa"]);
+        assert_eq!(feed.url, "https://cdn.example.com/channel.xml");
+        assert_eq!(feed.stats.episodes, 1);
+        assert_eq!(feed.added, vec!["aaaaaaaaaaa"]);
+        let written = std::fs::read_to_string(data_dir.path().join("channel.xml")).unwrap();
+        assert!(written.contains(r#"<guid isPermaLink="false">aaaaaaaaaaa</guid>"#));
+
+        let events = events.lock().unwrap().clone();
+        assert!(
+            matches!(&events[0], Event::ChannelStarted { channel, .. } if channel == "channel")
+        );
+        // The media file is a placeholder that cannot be probed.
+        assert!(matches!(&events[1], Event::Warning { message, .. } if message.contains("probe")));
+        assert!(matches!(&events[2], Event::FeedGenerated { stats, .. } if stats.episodes == 1));
+        assert!(matches!(&events[3], Event::ChannelFinished { .. }));
+
+        // A second generation has nothing new.
+        let feeds = smol::block_on(generator.generate()).unwrap();
+        assert!(feeds[0].added.is_empty());
+    }
+
+    #[test]
+    fn split_feeds_take_matching_episodes() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "channel");
+        let settings = Settings::from_toml(
+            r#"
+            [[channels."channel".split]]
+            name = "clips"
+            title_suffix = " (clips)"
+            rule = { max_duration = 600 }
+            "#,
+        )
+        .unwrap();
+
+        let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .settings(settings)
+            .build()
+            .unwrap();
+        let feeds = smol::block_on(generator.generate()).unwrap();
+
+        assert_eq!(feeds.len(), 2);
+        assert!(feeds[0].guids.is_empty());
+        assert_eq!(feeds[1].split.as_deref(), Some("clips"));
+        assert_eq!(feeds[1].url, "https://cdn.example.com/clips.xml");
+        assert_eq!(feeds[1].guids, vec!["aaaaaaaaaaa"]);
+        assert_eq!(feeds[1].added, vec!["aaaaaaaaaaa"]);
+        let written = std::fs::read_to_string(data_dir.path().join("clips.xml")).unwrap();
+        assert!(written.contains("<title>Example Channel (clips)</title>"));
+    }
+
+    /// The yt-dlp source, counting the episodes it parses.
+    struct Counting(Arc<AtomicUsize>, info::YtDlp);
+
+    impl MetadataSource for Counting {
+        fn discover_channel<'a>(
+            &'a self,
+            dirpath: &'a Path,
+        ) -> BoxFuture<'a, Result<Option<ChannelFile>>> {
+            self.1.discover_channel(dirpath)
+        }
+
+        fn discover_episodes<'a>(
+            &'a self,
+            dirpath: &'a Path,
+        ) -> BoxFuture<'a, Result<Vec<EpisodeFile>>> {
+            self.1.discover_episodes(dirpath)
+        }
+
+        fn parse_episode<'a>(
+            &'a self,
+            file: &'a EpisodeFile,
+        ) -> BoxFuture<'a, Result<Option<(source::Episode, source::Enclosure, PathBuf)>>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            self.1.parse_episode(file)
+        }
+
+        fn parse_channel<'a>(
+            &'a self,
+            dirpath: &'a Path,
+            file: Option<&'a ChannelFile>,
+            episodes: &'a [&'a source::Episode],
+        ) -> BoxFuture<'a, Result<(source::Channel, PathBuf)>> {
+            self.1.parse_channel(dirpath, file, episodes)
+        }
+    }
+
+    #[test]
+    fn channels_are_parsed_once_for_all_their_feeds() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "channel");
+        let settings = Settings::from_toml(
+            r#"
+            source = "counting"
+
+            [access]
+            secret = "secret"
+            subscribers = ["alice", "bob"]
+
+            [[channels."channel".split]]
+            name = "clips"
+            title_suffix = " (clips)"
+            rule = { max_duration = 600 }
+            "#,
+        )
+        .unwrap();
+        let parsed = Arc::new(AtomicUsize::new(0));
+
+        let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .settings(settings)
+            .source("counting", Counting(parsed.clone(), info::YtDlp::default()))
+            .build()
+            .unwrap();
+        let feeds = smol::block_on(generator.generate()).unwrap();
+
+        assert_eq!(feeds.len(), 4);
+        assert_eq!(parsed.load(Ordering::SeqCst), 1);
+        let episodes: Vec<usize> = feeds.iter().map(|feed| feed.stats.episodes).collect();
+        assert_eq!(episodes, [0, 0, 1, 1]);
+        // The figures about the parsing are counted once, in the first feed.
+        assert!(feeds[0].stats.warnings[0].contains("probe"));
+        assert!(feeds[1..].iter().all(|feed| feed.stats.warnings.is_empty()));
+    }
+
+    #[test]
+    fn feeds_with_the_same_name_are_rejected() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "Cooking");
+        channel(data_dir.path(), "Baking");
+        let split = |channel: &str, name: &str| {
+            format!(
+                "[[channels.\"{channel}\".split]]\nname = \"{name}\"\nrule = {{ max_duration = 600 }}\n"
+            )
+        };
+        let virtual_feed =
+            "[virtual.\"Clips\"]\ntitle = \"Clips\"\nartwork = \"clips.jpg\"\nchannels = []\n";
+
+        for (config, taken_by) in [
+            (
+                split("Cooking", "Clips") + &split("Baking", "Clips"),
+                "channels.Baking.split",
+            ),
+            (
+                split("Cooking", "Clips") + virtual_feed,
+                "channels.Cooking.split",
+            ),
+            (split("Cooking", "Baking"), "the channel directory `Baking`"),
+        ] {
+            let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+                .settings(Settings::from_toml(&config).unwrap())
+                .build()
+                .unwrap();
+            let error = smol::block_on(generator.generate()).unwrap_err();
+            assert!(
+                matches!(&error, Error::ConfigError(msg) if msg.contains(taken_by)),
+                "{config}: {error:?}"
+            );
+        }
+        assert!(!data_dir.path().join("Cooking.xml").exists());
+    }
+
+    /// Writes the titles of the episodes, one per line.
+    struct Titles;
+
+    impl FeedWriter for Titles {
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn mime_type(&self) -> &str {
+            "text/plain"
+        }
+
+        fn write(&self, channel: &rss::channel::Channel, output: &mut dyn Write) -> Result<()> {
+            for episode in &channel.episodes {
+                writeln!(output, "{}", episode.title)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn feeds_are_written_by_each_writer() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "channel");
+
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let recorder = paths.clone();
+        let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .writer(Titles)
+            .writer(RssWriter)
+            .on_event(move |event| {
+                if let Event::FeedGenerated { path, .. } = event {
+                    recorder.lock().unwrap().push(path);
+                }
+            })
+            .build()
+            .unwrap();
+        let feeds = smol::block_on(generator.generate()).unwrap();
+
+        let txt_filepath = data_dir.path().join("channel.txt");
+        let xml_filepath = data_dir.path().join("channel.xml");
+        assert_eq!(feeds[0].url, "https://cdn.example.com/channel.txt");
+        assert_eq!(*paths.lock().unwrap(), [Some(txt_filepath.clone())]);
+        assert_eq!(feeds[0].paths, [txt_filepath.clone(), xml_filepath.clone()]);
+        assert_eq!(std::fs::read_to_string(txt_filepath).unwrap(), "Episode\n");
+        assert!(xml_filepath.exists());
+        assert!(!data_dir.path().join("channel.xml.partial").exists());
+
+        let duplicate = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .writer(RssWriter)
+            .writer(RssWriter)
+            .build();
+        assert!(matches!(duplicate, Err(Error::ConfigError(msg)) if msg.contains("`xml`")));
+    }
+
+    /// Fails after writing the first line.
+    struct Failing;
+
+    impl FeedWriter for Failing {
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn mime_type(&self) -> &str {
+            "text/plain"
+        }
+
+        fn write(&self, _channel: &rss::channel::Channel, output: &mut dyn Write) -> Result<()> {
+            writeln!(output, "first line")?;
+            Err(std::io::Error::other("disk full").into())
+        }
+    }
+
+    #[test]
+    fn failed_writes_leave_no_partial_file() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "channel");
+        std::fs::write(data_dir.path().join("channel.txt"), "previous\n").unwrap();
+
+        let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .writer(Failing)
+            .build()
+            .unwrap();
+        let error = smol::block_on(generator.generate()).unwrap_err();
+
+        assert!(error.to_string().contains("disk full"));
+        assert!(!data_dir.path().join("channel.txt.partial").exists());
+        let previous = std::fs::read_to_string(data_dir.path().join("channel.txt")).unwrap();
+        assert_eq!(previous, "previous\n");
+    }
+
+    #[test]
+    fn memory_output_writes_nothing() {
+        let data_dir = tempfile::tempdir().unwrap();
+        channel(data_dir.path(), "channel");
+
+        let generator = FeedGenerator::builder(data_dir.path(), "https://cdn.example.com")
+            .output(Output::Memory)
+            .build()
+            .unwrap();
+        let feeds =
+            smol::block_on(generator.generate_channel(&data_dir.path().join("channel"))).unwrap();
+
+        assert!(feeds[0].paths.is_empty());
+        let rss = feeds[0].rss.as_ref().unwrap();
+        assert_eq!(rss.channel.title, "Example Channel");
+        assert!(!data_dir.path().join("channel.xml").exists());
+    }
+
+    #[test]
+    fn invalid_base_url_is_rejected() {
+        assert!(
+            FeedGenerator::builder("/data", "not a url")
+                .build()
+                .is_err()
+        );
+    }
+}
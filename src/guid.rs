@@ -0,0 +1,130 @@
+//! GUIDs of the episodes.
+//!
+//! Podcast apps identify episodes by their GUID, so changing the scheme of a published feed
+//! makes every episode show up again as new. The `legacy` table maps YouTube IDs to the GUIDs
+//! already published, to keep them when a channel is migrated:
+//!
+//! ```toml
+//! [guid]
+//! scheme = "yt-video"
+//!
+//! [channels."Cooking".guid.legacy]
+//! "dQw4w9WgXcQ" = "cooking-episode-12"
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{Result, error::Error, rss};
+
+/// Format of the GUIDs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scheme {
+    /// The YouTube ID, e.g. `dQw4w9WgXcQ`.
+    #[default]
+    Raw,
+    /// The YouTube ID with a prefix, e.g. `yt:video:dQw4w9WgXcQ`.
+    YtVideo,
+    /// The URL of the webpage of the episode, which is a permalink.
+    Url,
+    /// A UUIDv5 of the YouTube ID in the configured namespace.
+    Uuid,
+}
+
+/// GUID settings of a channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Format of the GUIDs.
+    pub scheme: Scheme,
+    /// Namespace of the UUIDs, required by the `uuid` scheme.
+    pub namespace: Option<Uuid>,
+    /// GUIDs to keep, keyed by YouTube ID, regardless of the scheme.
+    pub legacy: BTreeMap<String, String>,
+}
+
+impl Settings {
+    /// Return the GUID of the episode with the YouTube `id` and the webpage `link`.
+    pub fn guid(&self, id: &str, link: &str) -> Result<rss::episode::Guid> {
+        if let Some(legacy) = self.legacy.get(id) {
+            return Ok(guid(legacy.clone(), false));
+        }
+        let guid = match self.scheme {
+            Scheme::Raw => guid(id.to_string(), false),
+            Scheme::YtVideo => guid(format!("yt:video:{id}"), false),
+            Scheme::Url => guid(link.to_string(), true),
+            Scheme::Uuid => {
+                let namespace = self.namespace.ok_or_else(|| {
+                    Error::ConfigError("the `uuid` GUID scheme requires a `namespace`".into())
+                })?;
+                guid(Uuid::new_v5(&namespace, id.as_bytes()).to_string(), false)
+            }
+        };
+        Ok(guid)
+    }
+}
+
+fn guid(value: String, is_perma_link: bool) -> rss::episode::Guid {
+    rss::episode::Guid {
+        is_perma_link: Some(is_perma_link),
+        value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: &str = "https://www.youtube.com/watch?v=abc123";
+
+    fn settings(scheme: Scheme) -> Settings {
+        Settings {
+            scheme,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn guids_follow_the_scheme() {
+        let raw = settings(Scheme::Raw).guid("abc123", LINK).unwrap();
+        assert_eq!(raw, guid("abc123".into(), false));
+
+        let yt_video = settings(Scheme::YtVideo).guid("abc123", LINK).unwrap();
+        assert_eq!(yt_video.value, "yt:video:abc123");
+
+        let url = settings(Scheme::Url).guid("abc123", LINK).unwrap();
+        assert_eq!(url, guid(LINK.into(), true));
+
+        assert!(matches!(
+            settings(Scheme::Uuid).guid("abc123", LINK),
+            Err(Error::ConfigError(_))
+        ));
+        let uuid = Settings {
+            namespace: Some(Uuid::NAMESPACE_URL),
+            ..settings(Scheme::Uuid)
+        };
+        let first = uuid.guid("abc123", LINK).unwrap();
+        assert_eq!(first, uuid.guid("abc123", LINK).unwrap());
+        assert_ne!(first, uuid.guid("def456", LINK).unwrap());
+        assert_eq!(first.value.len(), 36);
+    }
+
+    #[test]
+    fn legacy_guids_are_kept() {
+        let settings: Settings = toml::from_str(
+            r#"
+            scheme = "url"
+            legacy = { abc123 = "episode-12" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.guid("abc123", LINK).unwrap(),
+            guid("episode-12".into(), false)
+        );
+        assert_eq!(settings.guid("def456", LINK).unwrap().value, LINK);
+    }
+}
@@ -0,0 +1,216 @@
+//! Episode history from the previously published feed.
+//!
+//! When yt-dlp downloads an episode again or its metadata changes, the publication date or
+//! the title of the episode may change, and podcast apps then show it again as a new episode.
+//! To avoid that, the sticky fields of each episode are copied from the previous version of
+//! the feed, matched by GUID.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use hard_xml::XmlRead;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{Result, rss};
+
+/// Episode fields that can be kept from the previous feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Field {
+    /// Publication date, `pubDate`.
+    PubDate,
+    /// Title.
+    Title,
+    /// Description.
+    Description,
+    /// Link to the webpage of the episode.
+    Link,
+}
+
+/// History settings of a channel.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Fields kept from the previous feed. When empty, the previous feed is ignored.
+    pub sticky: Vec<Field>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sticky: vec![Field::PubDate],
+        }
+    }
+}
+
+/// Read the feed previously written to `filepath`, if any.
+///
+/// The feed is read whole into memory, as it is parsed from a string.
+pub fn load<P: AsRef<Path>>(filepath: P) -> Result<Option<rss::Rss>> {
+    match std::fs::read_to_string(filepath) {
+        Ok(content) => Ok(Some(rss::Rss::from_str(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Settings {
+    /// Remove the episodes of `channel` with the GUID of an earlier episode, and copy the
+    /// sticky fields of the episodes found in the `previous` channel.
+    pub fn apply(
+        &self,
+        previous: Option<&rss::channel::Channel>,
+        channel: &mut rss::channel::Channel,
+    ) {
+        let mut guids = HashSet::new();
+        channel.episodes.retain(|episode| {
+            let first = guids.insert(episode.guid.value.clone());
+            if !first {
+                debug!(guid = %episode.guid.value, "skipping episode: duplicate GUID");
+            }
+            first
+        });
+
+        let Some(previous) = previous else {
+            return;
+        };
+        let previous_episodes: HashMap<&str, &rss::episode::Episode> = previous
+            .episodes
+            .iter()
+            .map(|episode| (episode.guid.value.as_str(), episode))
+            .collect();
+
+        for episode in &mut channel.episodes {
+            let Some(previous_episode) = previous_episodes.get(episode.guid.value.as_str()) else {
+                continue;
+            };
+            for field in &self.sticky {
+                match field {
+                    Field::PubDate => episode.pub_date.clone_from(&previous_episode.pub_date),
+                    Field::Title => {
+                        episode.title.clone_from(&previous_episode.title);
+                        if episode.itunes_title.is_some() {
+                            episode.itunes_title = Some(previous_episode.title.clone());
+                        }
+                    }
+                    Field::Description => {
+                        episode
+                            .description
+                            .clone_from(&previous_episode.description);
+                        if previous_episode.summary.is_some() {
+                            episode.summary.clone_from(&previous_episode.summary);
+                        }
+                        if previous_episode.subtitle.is_some() {
+                            episode.subtitle.clone_from(&previous_episode.subtitle);
+                        }
+                    }
+                    Field::Link => episode.link.clone_from(&previous_episode.link),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rss::{channel::Channel, episode::Episode};
+
+    fn episode(guid: &str, pub_date: &str, title: &str) -> Episode {
+        Episode {
+            guid: rss::episode::Guid {
+                is_perma_link: Some(false),
+                value: guid.into(),
+            },
+            pub_date: pub_date.into(),
+            title: title.into(),
+            link: format!("https://youtube.com/watch?v={guid}"),
+            description: "description".into(),
+            enclosure: rss::episode::Enclosure {
+                file_url: format!("https://cdn.example.com/{guid}.mp4"),
+                file_length: "1".into(),
+                file_type: "video/mp4".into(),
+            },
+            author: "Author".into(),
+            image: rss::episode::Image {
+                file_url: format!("https://cdn.example.com/{guid}.png"),
+            },
+            duration: None,
+            explicit_content: "false".into(),
+            itunes_title: None,
+            subtitle: None,
+            summary: None,
+            keywords: None,
+        }
+    }
+
+    fn channel(episodes: Vec<Episode>) -> Channel {
+        Channel {
+            title: "Channel".into(),
+            description: "Description".into(),
+            link: "https://example.com".into(),
+            image: rss::channel::Image {
+                image_url: "https://cdn.example.com/channel.jpg".into(),
+            },
+            author: "Author".into(),
+            language: "en".into(),
+            last_build_date: "Mon, 01 Jan 2024 00:00:00 +0000".into(),
+            pub_date: "Mon, 01 Jan 2024 00:00:00 +0000".into(),
+            categories: vec![rss::channel::Category {
+                text: "Technology".into(),
+                subcategories: Vec::new(),
+            }],
+            generator: "ytdlp".into(),
+            explicit_content: "false".into(),
+            channel_type: "Serial".into(),
+            episodes,
+        }
+    }
+
+    #[test]
+    fn sticky_fields_are_kept_and_duplicates_removed() {
+        let previous = channel(vec![episode("a", "Sun, 01 Jan 2023", "Old title")]);
+        let mut current = channel(vec![
+            episode("a", "Fri, 01 Mar 2024", "New title"),
+            episode("b", "Sat, 02 Mar 2024", "Other"),
+            episode("a", "Sun, 03 Mar 2024", "Again"),
+        ]);
+
+        Settings::default().apply(Some(&previous), &mut current);
+
+        assert_eq!(current.episodes.len(), 2);
+        assert_eq!(current.episodes[0].pub_date, "Sun, 01 Jan 2023");
+        assert_eq!(current.episodes[0].title, "New title");
+        assert_eq!(current.episodes[1].pub_date, "Sat, 02 Mar 2024");
+
+        let settings = Settings {
+            sticky: vec![Field::Title],
+        };
+        let mut current = channel(vec![episode("a", "Fri, 01 Mar 2024", "New title")]);
+        settings.apply(Some(&previous), &mut current);
+        assert_eq!(current.episodes[0].pub_date, "Fri, 01 Mar 2024");
+        assert_eq!(current.episodes[0].title, "Old title");
+    }
+
+    #[test]
+    fn load_reads_previous_feed() {
+        let directory = tempfile::tempdir().unwrap();
+        let filepath = directory.path().join("channel.xml");
+        assert!(load(&filepath).unwrap().is_none());
+
+        let feed = rss::Rss {
+            version: "2.0".into(),
+            namespace: "http://www.itunes.com/dtds/podcast-1.0.dtd".into(),
+            content_namespace: "http://purl.org/rss/1.0/modules/content/".into(),
+            channel: channel(vec![episode("a", "Sun, 01 Jan 2023", "Title")]),
+        };
+        std::fs::write(&filepath, crate::convert::render(&feed).unwrap()).unwrap();
+        assert_eq!(load(&filepath).unwrap(), Some(feed));
+
+        std::fs::write(&filepath, "<rss>").unwrap();
+        assert!(load(&filepath).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Commands run after feeds are written.
+//!
+//! Channel hooks run after each feed of a channel is written, and run hooks run once after
+//! all feeds are written. Hooks receive the details of the feeds in `PODFEED_*` environment
+//! variables, and optionally as a JSON document on their standard input.
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use smol::{Timer, io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+
+use crate::{Result, error::Error};
+
+/// How a hook receives the details of the feeds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Input {
+    /// In environment variables only.
+    #[default]
+    Env,
+    /// In environment variables, and as a JSON document on standard input.
+    Json,
+}
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum When {
+    /// After every generation.
+    #[default]
+    Always,
+    /// Only when episodes were added or removed.
+    Changed,
+}
+
+/// What to do when a hook fails, times out, or cannot be started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnFailure {
+    /// Carry on silently.
+    Ignore,
+    /// Print a warning and carry on.
+    #[default]
+    Warn,
+    /// Stop and exit with an error.
+    Abort,
+}
+
+/// A command to run after feeds are written.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// Program and arguments, run without a shell.
+    #[serde(deserialize_with = "deserialize_command")]
+    pub command: Vec<String>,
+    /// How the hook receives the details of the feeds.
+    #[serde(default)]
+    pub input: Input,
+    /// When the hook runs.
+    #[serde(default)]
+    pub when: When,
+    /// Seconds after which the hook is killed.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// What to do when the hook fails.
+    #[serde(default)]
+    pub on_failure: OnFailure,
+}
+
+/// Hooks of a channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Hooks run after each feed of the channel is written.
+    pub channel: Vec<Hook>,
+}
+
+/// A feed that has just been written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedUpdate {
+    /// Name of the channel directory.
+    pub channel: String,
+    /// Path of the channel directory.
+    pub channel_path: PathBuf,
+    /// Path of the feed file.
+    pub feed_path: PathBuf,
+    /// Public URL of the feed.
+    pub feed_url: String,
+    /// Subscriber of the feed, for private feeds with subscribers.
+    pub subscriber: Option<String>,
+    /// GUIDs of the episodes absent from the previous version of the feed.
+    pub added: Vec<String>,
+    /// GUIDs of the episodes no longer in the feed.
+    pub removed: Vec<String>,
+}
+
+impl FeedUpdate {
+    /// Return whether episodes were added or removed.
+    pub fn is_changed(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+
+    /// Run the channel `hooks` for this feed.
+    pub async fn run_hooks(&self, hooks: &[Hook]) -> Result<()> {
+        let env = [
+            ("PODFEED_CHANNEL", self.channel.clone()),
+            (
+                "PODFEED_CHANNEL_PATH",
+                self.channel_path.display().to_string(),
+            ),
+            ("PODFEED_FEED_PATH", self.feed_path.display().to_string()),
+            ("PODFEED_FEED_URL", self.feed_url.clone()),
+            (
+                "PODFEED_SUBSCRIBER",
+                self.subscriber.clone().unwrap_or_default(),
+            ),
+            ("PODFEED_ADDED_GUIDS", self.added.join("\n")),
+            ("PODFEED_REMOVED_GUIDS", self.removed.join("\n")),
+        ];
+        let document = serde_json::to_vec(self)?;
+        run_hooks(hooks, self.is_changed(), &env, &document).await
+    }
+}
+
+/// All the feeds written during a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunSummary {
+    /// Data directory.
+    pub data_dir: PathBuf,
+    /// Feeds written during the run.
+    pub feeds: Vec<FeedUpdate>,
+}
+
+impl RunSummary {
+    /// Return whether episodes were added to or removed from any feed.
+    pub fn is_changed(&self) -> bool {
+        self.feeds.iter().any(FeedUpdate::is_changed)
+    }
+
+    /// Run the run `hooks` for all the feeds.
+    pub async fn run_hooks(&self, hooks: &[Hook]) -> Result<()> {
+        let join = |f: fn(&FeedUpdate) -> Vec<String>| {
+            self.feeds.iter().flat_map(f).collect::<Vec<_>>().join("\n")
+        };
+        let env = [
+            ("PODFEED_DATA_DIR", self.data_dir.display().to_string()),
+            (
+                "PODFEED_FEED_PATHS",
+                join(|feed| vec![feed.feed_path.display().to_string()]),
+            ),
+            ("PODFEED_ADDED_GUIDS", join(|feed| feed.added.clone())),
+            ("PODFEED_REMOVED_GUIDS", join(|feed| feed.removed.clone())),
+        ];
+        let document = serde_json::to_vec(self)?;
+        run_hooks(hooks, self.is_changed(), &env, &document).await
+    }
+}
+
+/// Return the GUIDs added to and removed from `previous` to get `current`.
+pub fn diff(previous: &[String], current: &[String]) -> (Vec<String>, Vec<String>) {
+    let previous_set: HashSet<&String> = previous.iter().collect();
+    let current_set: HashSet<&String> = current.iter().collect();
+    let added = current
+        .iter()
+        .filter(|guid| !previous_set.contains(guid))
+        .cloned()
+        .collect();
+    let removed = previous
+        .iter()
+        .filter(|guid| !current_set.contains(guid))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+async fn run_hooks(
+    hooks: &[Hook],
+    changed: bool,
+    env: &[(&str, String)],
+    document: &[u8],
+) -> Result<()> {
+    for hook in hooks {
+        let command = hook.command.join(" ");
+        if hook.when == When::Changed && !changed {
+            debug!(hook = %command, "skipping hook: no episode added or removed");
+            continue;
+        }
+        if let Err(reason) = execute(hook, env, document).await {
+            match hook.on_failure {
+                OnFailure::Ignore => {}
+                OnFailure::Warn => warn!(hook = %command, "hook failed: {reason}"),
+                OnFailure::Abort => return Err(Error::Hook(command, reason)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run `hook` to completion, returning the reason of a failure.
+async fn execute(
+    hook: &Hook,
+    env: &[(&str, String)],
+    document: &[u8],
+) -> std::result::Result<(), String> {
+    let mut command = Command::new(&hook.command[0]);
+    command
+        .args(&hook.command[1..])
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .kill_on_drop(true);
+    command.stdin(match hook.input {
+        Input::Env => Stdio::null(),
+        Input::Json => Stdio::piped(),
+    });
+    let mut child = command.spawn().map_err(|e| e.to_string())?;
+
+    let stdin = child.stdin.take();
+    let completion = async {
+        if let Some(mut stdin) = stdin {
+            // The hook may exit without reading its input.
+            let _ = stdin.write_all(document).await;
+        }
+        Some(child.status().await)
+    };
+    let timeout = async {
+        Timer::after(Duration::from_secs(hook.timeout)).await;
+        None
+    };
+
+    let status: ExitStatus = match smol::future::or(completion, timeout).await {
+        Some(status) => status.map_err(|e| e.to_string())?,
+        None => {
+            let _ = child.kill();
+            let _ = child.status().await;
+            return Err(format!("timed out after {} seconds", hook.timeout));
+        }
+    };
+    if !status.success() {
+        return Err(status.to_string());
+    }
+    Ok(())
+}
+
+fn default_timeout() -> u64 {
+    60
+}
+
+fn deserialize_command<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let command = Vec::<String>::deserialize(deserializer)?;
+    if command.is_empty() {
+        return Err(serde::de::Error::custom("hook command must not be empty"));
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(script: &str) -> Hook {
+        Hook {
+            command: vec!["sh".into(), "-c".into(), script.into()],
+            input: Input::Env,
+            when: When::Always,
+            timeout: 10,
+            on_failure: OnFailure::Abort,
+        }
+    }
+
+    fn update(added: &[&str]) -> FeedUpdate {
+        FeedUpdate {
+            channel: "Cooking".into(),
+            channel_path: "/data/Cooking".into(),
+            feed_path: "/data/Cooking.xml".into(),
+            feed_url: "https://example.com/Cooking.xml".into(),
+            subscriber: None,
+            added: added.iter().map(|guid| guid.to_string()).collect(),
+            removed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn guids_are_diffed() {
+        let previous = vec!["a".to_string(), "b&c".to_string()];
+        let current = vec!["b&c".to_string(), "d".to_string()];
+        let (added, removed) = diff(&previous, &current);
+        assert_eq!(added, vec!["d"]);
+        assert_eq!(removed, vec!["a"]);
+    }
+
+    #[test]
+    fn hook_receives_env_and_json() {
+        let directory = tempfile::tempdir().unwrap();
+        let output = directory.path().join("output");
+        let script = format!(
+            "printf '%s|%s|' \"$PODFEED_CHANNEL\" \"$PODFEED_ADDED_GUIDS\" >{0} && cat >>{0}",
+            output.display()
+        );
+        let hooks = [Hook {
+            input: Input::Json,
+            ..hook(&script)
+        }];
+
+        smol::block_on(update(&["a", "b"]).run_hooks(&hooks)).unwrap();
+
+        let content = std::fs::read_to_string(output).unwrap();
+        let (env, json) = content.split_at(content.find('{').unwrap());
+        assert_eq!(env, "Cooking|a\nb|");
+        let json: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(json["feed_url"], "https://example.com/Cooking.xml");
+        assert_eq!(json["added"], serde_json::json!(["a", "b"]));
+    }
+
+    #[test]
+    fn changed_hooks_are_skipped_without_changes() {
+        let hooks = [Hook {
+            when: When::Changed,
+            ..hook("exit 1")
+        }];
+        smol::block_on(update(&[]).run_hooks(&hooks)).unwrap();
+        assert!(smol::block_on(update(&["a"]).run_hooks(&hooks)).is_err());
+    }
+
+    #[test]
+    fn failures_follow_the_policy() {
+        let error = smol::block_on(update(&[]).run_hooks(&[hook("exit 3")])).unwrap_err();
+        assert!(matches!(error, Error::Hook(_, reason) if reason.contains('3')));
+
+        let timeout = Hook {
+            timeout: 1,
+            ..hook("sleep 30")
+        };
+        let error = smol::block_on(update(&[]).run_hooks(&[timeout])).unwrap_err();
+        assert!(matches!(error, Error::Hook(_, reason) if reason.contains("timed out")));
+
+        let ignored = Hook {
+            on_failure: OnFailure::Ignore,
+            ..hook("exit 1")
+        };
+        let missing = Hook {
+            command: vec!["/nonexistent/podfeed-hook".into()],
+            on_failure: OnFailure::Warn,
+            ..hook("")
+        };
+        smol::block_on(update(&[]).run_hooks(&[ignored, missing])).unwrap();
+    }
+}
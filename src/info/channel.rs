@@ -2,12 +2,12 @@
 
 use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Local, NaiveDate, offset::Utc};
+use chrono::NaiveDate;
 use futures::stream::StreamExt;
 use regex::Regex;
 use serde::Deserialize;
 
-use crate::{Result, error::Error};
+use crate::{Result, error::Error, source};
 
 /// Represents the info.json file of a channel.
 #[derive(Debug, Clone)]
@@ -21,8 +21,7 @@ pub struct InfoFile {
 impl InfoFile {
     /// Parse the associated `Info` and return it along with the image url.
     pub async fn parse(&self) -> Result<(Info, PathBuf)> {
-        let content = smol::fs::read_to_string(&self.filepath).await?;
-        let ch_info: Info = serde_json::from_str(&content)?;
+        let ch_info: Info = super::read(&self.filepath).await?;
 
         let image_filepath = self
             .filepath
@@ -35,9 +34,8 @@ impl InfoFile {
     }
 }
 
-/// Return the channel file in `dirpath`. If no file is found, or if multiple files are found,
-/// return an error.
-pub async fn available_channel<P: AsRef<Path>>(dirpath: P) -> Result<InfoFile> {
+/// Return the channel files in `dirpath`, ordered by filename.
+pub async fn available_channels<P: AsRef<Path>>(dirpath: P) -> Result<Vec<InfoFile>> {
     let mut files: Vec<InfoFile> = vec![];
 
     let pattern = r#"^NA--([a-zA-Z0-9-_]{11,34}?)--.+\.info\.json$"#;
@@ -61,13 +59,122 @@ pub async fn available_channel<P: AsRef<Path>>(dirpath: P) -> Result<InfoFile> {
         }
     }
 
+    files.sort_by(|a, b| a.filepath.cmp(&b.filepath));
+    Ok(files)
+}
+
+/// Return the channel file in `dirpath`. If no file is found, or if multiple files are found,
+/// return an error.
+pub async fn available_channel<P: AsRef<Path>>(dirpath: P) -> Result<InfoFile> {
+    let mut files = available_channels(dirpath.as_ref()).await?;
     match files.len() {
         0 => Err(Error::MissingChannelInfoFile(dirpath.as_ref().into())),
-        1 => Ok(files.first().unwrap().clone()),
+        1 => Ok(files.remove(0)),
         _ => Err(Error::MultipleChannelInfoFiles(dirpath.as_ref().into())),
     }
 }
 
+/// Policy choosing among several channel files in a channel directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resolve {
+    /// Use the channel file with the newest `modified_date`.
+    #[default]
+    Newest,
+    /// Use the channel file of the playlist [`Settings::id`].
+    Pinned,
+    /// Use the newest channel file, with the descriptions of the others appended.
+    Merge,
+}
+
+/// Resolution of several channel files, e.g. after a playlist was renamed or downloaded from
+/// two URLs:
+///
+/// ```toml
+/// [channels."Talks".playlist]
+/// resolve = "pinned"
+/// id = "PLabcdefghijk"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Policy choosing among several channel files.
+    pub resolve: Resolve,
+    /// Playlist ID of the channel file used with [`Resolve::Pinned`].
+    pub id: Option<String>,
+}
+
+/// The modification date of a channel file, to order candidates.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Modified {
+    modified_date: Option<String>,
+}
+
+/// Return the channel file of `dirpath`, choosing among several channel files with
+/// `settings`, and warning about the candidates.
+///
+/// If the pinned playlist is not among the candidates, the newest channel file is used, so
+/// a renamed playlist does not take the feed offline.
+pub async fn resolve<P: AsRef<Path>>(
+    dirpath: P,
+    settings: &Settings,
+) -> Result<source::ChannelFile> {
+    let dirpath = dirpath.as_ref();
+    let mut candidates = Vec::new();
+    for infofile in available_channels(dirpath).await? {
+        let modified: Modified = super::read(&infofile.filepath).await?;
+        candidates.push((modified.modified_date.unwrap_or_default(), infofile));
+    }
+    match candidates.len() {
+        0 => return Err(Error::MissingChannelInfoFile(dirpath.into())),
+        1 => return Ok(source::ChannelFile::new(candidates.remove(0).1.filepath)),
+        _ => (),
+    }
+
+    // Newest first, in the order of the filenames on ties.
+    candidates.sort_by(|(a, _), (b, _)| b.cmp(a));
+    let listing = candidates
+        .iter()
+        .map(|(modified_date, infofile)| format!("{} ({modified_date})", infofile.youtube_id))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let pinned = match (settings.resolve, &settings.id) {
+        (Resolve::Pinned, None) => {
+            return Err(Error::ConfigError(
+                "playlist.resolve = \"pinned\" requires a playlist.id".into(),
+            ));
+        }
+        (Resolve::Pinned, Some(id)) => candidates
+            .iter()
+            .position(|(_, infofile)| &infofile.youtube_id == id),
+        _ => None,
+    };
+    let mut filepaths: Vec<PathBuf> = candidates
+        .into_iter()
+        .map(|(_, infofile)| infofile.filepath)
+        .collect();
+    let chosen = filepaths.remove(pinned.unwrap_or(0));
+    let note = match (settings.resolve, pinned) {
+        (Resolve::Pinned, Some(_)) => "the pinned playlist".to_string(),
+        (Resolve::Pinned, None) => format!(
+            "the newest, since the pinned playlist `{}` is missing",
+            settings.id.as_deref().unwrap_or_default()
+        ),
+        (Resolve::Newest, _) => "the newest".to_string(),
+        (Resolve::Merge, _) => "the newest, with the other descriptions merged".to_string(),
+    };
+    let mut file = source::ChannelFile::new(chosen);
+    file.warnings.push(format!(
+        "several channel info files in {}: {listing}; using {note}",
+        dirpath.display()
+    ));
+    if settings.resolve == Resolve::Merge {
+        file.merged = filepaths;
+    }
+    Ok(file)
+}
+
 /// The content of an info.json file for a channel.
 #[derive(Debug, Clone, Deserialize)]
 pub struct Info {
@@ -91,39 +198,96 @@ pub struct Info {
     /// Author (usually the channel name).
     #[serde(rename = "channel")]
     pub author: String,
-}
-
-impl Info {
-    pub(crate) fn pub_date(&self) -> DateTime<Utc> {
-        let naived_date = NaiveDate::parse_from_str(&self.upload_date, "%Y%m%d")
-            .unwrap()
-            .and_hms_opt(9, 10, 11)
-            .unwrap();
-        DateTime::<Utc>::from_naive_utc_and_offset(naived_date, Utc)
-    }
-
-    pub(crate) fn language(&self) -> &'static str {
-        "en"
-    }
-
-    pub(crate) fn category(&self) -> &'static str {
-        "Technology & Science"
-    }
 
-    pub(crate) fn last_build_date(&self) -> DateTime<Local> {
-        Local::now()
-    }
+    /// Language of the channel, if reported by yt-dlp.
+    #[serde(default)]
+    pub language: Option<String>,
+}
 
-    pub(crate) fn generator(&self) -> &'static str {
-        "ytdlp"
-    }
+/// The channel fields of the info.json file of an episode.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct EpisodeChannel {
+    /// Name of the channel.
+    channel: Option<String>,
+    /// Channel webpage link (youtube page).
+    channel_url: Option<String>,
+    /// Name of the uploader, usually the channel name.
+    uploader: Option<String>,
+    /// Title of the downloaded playlist, e.g. `<channel> - Videos` for a channel tab.
+    playlist_title: Option<String>,
+}
 
-    pub(crate) fn explicit_content(&self) -> &'static str {
-        "false"
-    }
+/// Return the metadata of the channel directory `dirpath`, which has no channel info file,
+/// from the info file of the newest of its `episodes`, along with the image of that episode.
+///
+/// The title is the playlist title, the channel name, the uploader or else the directory
+/// name, and the description defaults to the title.
+pub async fn from_episodes(
+    dirpath: &Path,
+    episodes: &[&source::Episode],
+) -> Result<(source::Channel, PathBuf)> {
+    let missing = || Error::MissingChannelInfoFile(dirpath.into());
+    let newest = episodes
+        .iter()
+        .max_by_key(|episode| episode.upload_date)
+        .ok_or_else(missing)?;
+    let infofile = super::episode::available_episodes(dirpath)
+        .await?
+        .into_iter()
+        .find(|infofile| infofile.youtube_id == newest.guid)
+        .ok_or_else(missing)?;
+    let fields: EpisodeChannel = super::read(&infofile.filepath).await?;
+
+    let title = fields
+        .playlist_title
+        .or_else(|| fields.channel.clone())
+        .or_else(|| fields.uploader.clone())
+        .unwrap_or_else(|| {
+            dirpath
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into()
+        });
+    let channel = source::Channel {
+        upload_date: newest.upload_date,
+        description: title.clone(),
+        link: fields.channel_url,
+        author: fields
+            .channel
+            .or(fields.uploader)
+            .unwrap_or_else(|| title.clone()),
+        title,
+        language: None,
+    };
+
+    let image_filepath = infofile
+        .filepath
+        // remove ".json"
+        .with_extension("")
+        // replace ".info" with ".png", like the episode image
+        .with_extension("png");
+    Ok((channel, image_filepath))
+}
 
-    pub(crate) fn channel_type(&self) -> &'static str {
-        "Serial"
+impl TryFrom<Info> for source::Channel {
+    type Error = Error;
+
+    fn try_from(info: Info) -> Result<Self> {
+        let upload_date = NaiveDate::parse_from_str(&info.upload_date, "%Y%m%d").map_err(|_| {
+            Error::MissingMetadata(format!(
+                "invalid modified date `{}` of channel {}",
+                info.upload_date, info.title
+            ))
+        })?;
+        Ok(Self {
+            upload_date,
+            title: info.title,
+            description: info.description,
+            link: Some(info.link),
+            author: info.author,
+            language: info.language,
+        })
     }
 }
 
@@ -154,7 +318,7 @@ mod tests {
     #[test]
     fn pub_date_parses_yyyymmdd() {
         let info: Info = serde_json::from_str(sample_json()).unwrap();
-        let dt = info.pub_date();
+        let dt = source::Channel::try_from(info).unwrap().pub_date();
         assert_eq!(dt.format("%Y-%m-%d").to_string(), "2025-06-01");
     }
 
@@ -215,11 +379,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn resolve_chooses_among_several_channel_files() {
+        use crate::source::MetadataSource;
+
+        let directory = tempfile::tempdir().unwrap();
+        for (id, modified_date, description) in [
+            ("PLabcdefghijk", "20230101", "Old description"),
+            ("PLmnopqrstuvw", "20240101", "New description"),
+        ] {
+            std::fs::write(
+                directory
+                    .path()
+                    .join(format!("NA--{id}--Playlist.info.json")),
+                serde_json::json!({
+                    "modified_date": modified_date,
+                    "title": id,
+                    "description": description,
+                    "webpage_url": format!("https://www.youtube.com/playlist?list={id}"),
+                    "channel": "Author"
+                })
+                .to_string(),
+            )
+            .unwrap();
+        }
+        let settings = |resolve, id: Option<&str>| Settings {
+            resolve,
+            id: id.map(String::from),
+        };
+        let resolved = |settings| smol::block_on(resolve(directory.path(), &settings));
+        let is_newest =
+            |file: &source::ChannelFile| file.filepath.to_string_lossy().contains("PLmnopqrstuvw");
+
+        let newest = resolved(settings(Resolve::Newest, None)).unwrap();
+        assert!(is_newest(&newest));
+        assert!(newest.merged.is_empty());
+        assert_eq!(newest.warnings.len(), 1);
+        assert!(newest.warnings[0].contains("PLmnopqrstuvw (20240101), PLabcdefghijk (20230101)"));
+
+        let pinned = resolved(settings(Resolve::Pinned, Some("PLabcdefghijk"))).unwrap();
+        assert!(!is_newest(&pinned));
+        // The yt-dlp source is configured with the policy of the channel.
+        let channel_settings = crate::settings::ChannelSettings {
+            playlist: settings(Resolve::Pinned, Some("PLabcdefghijk")),
+            ..Default::default()
+        };
+        let source = source::Sources::default().get(&channel_settings).unwrap();
+        let discovered = smol::block_on(source.discover_channel(directory.path())).unwrap();
+        assert!(!is_newest(&discovered.unwrap()));
+        let renamed = resolved(settings(Resolve::Pinned, Some("PLrenamed1234"))).unwrap();
+        assert!(is_newest(&renamed));
+        assert!(renamed.warnings[0].contains("`PLrenamed1234` is missing"));
+        let unpinned = resolved(settings(Resolve::Pinned, None)).unwrap_err();
+        assert!(matches!(unpinned, Error::ConfigError(_)));
+
+        let merged = resolved(settings(Resolve::Merge, None)).unwrap();
+        assert!(is_newest(&merged));
+        assert_eq!(merged.merged.len(), 1);
+        let (channel, _) = smol::block_on(super::super::YtDlp::default().parse_channel(
+            directory.path(),
+            Some(&merged),
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(channel.title, "PLmnopqrstuvw");
+        assert_eq!(channel.description, "New description\n\nOld description");
+    }
+
     #[test]
     fn hardcoded_defaults() {
         let info: Info = serde_json::from_str(sample_json()).unwrap();
+        let info = source::Channel::try_from(info).unwrap();
         assert_eq!(info.language(), "en");
-        assert_eq!(info.category(), "Technology & Science");
         assert_eq!(info.generator(), "ytdlp");
         assert_eq!(info.explicit_content(), "false");
         assert_eq!(info.channel_type(), "Serial");
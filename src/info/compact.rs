@@ -0,0 +1,131 @@
+//! Strip the bulky keys of the `.info.json` files written by yt-dlp.
+//!
+//! Episode info files weigh 200 KB to 2 MB, mostly in the lists of formats, thumbnails,
+//! captions and heatmap, which podfeed never reads. Compacting rewrites them without these
+//! keys, and records the version of the compaction in the [`VERSION_KEY`] key. The original
+//! file is kept as `<stem>.info.orig.json`, which matches neither the episode nor the channel
+//! info file patterns.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::de::IgnoredAny;
+
+use crate::Result;
+
+/// Version of the compaction, bumped whenever [`BULKY_KEYS`] changes.
+pub const VERSION: u64 = 1;
+
+/// Key recording the version of the compaction in compacted info files.
+pub const VERSION_KEY: &str = "_podfeed_compact";
+
+/// Keys removed from the info files.
+pub const BULKY_KEYS: [&str; 7] = [
+    "formats",
+    "requested_formats",
+    "requested_downloads",
+    "thumbnails",
+    "automatic_captions",
+    "subtitles",
+    "heatmap",
+];
+
+/// Return the filepath of the backup of the info file at `filepath`.
+pub fn backup_filepath<P: AsRef<Path>>(filepath: P) -> PathBuf {
+    // replace ".json" with ".orig.json"
+    filepath.as_ref().with_extension("orig.json")
+}
+
+/// Return `true` if the info file at `filepath` has any of the [`BULKY_KEYS`].
+///
+/// Only the keys are read, the values are skipped.
+pub fn needs_compaction<P: AsRef<Path>>(filepath: P) -> Result<bool> {
+    let reader = BufReader::new(File::open(filepath.as_ref())?);
+    let keys: BTreeMap<String, IgnoredAny> = serde_json::from_reader(reader)?;
+    Ok(BULKY_KEYS.iter().any(|key| keys.contains_key(*key)))
+}
+
+/// Rewrite the info file at `filepath` without the [`BULKY_KEYS`], after moving the original
+/// to its [`backup_filepath`] unless a backup already exists.
+///
+/// The new file is written next to the original and renamed over it once complete. Return
+/// `false` if the file had none of the bulky keys.
+pub fn compact<P: AsRef<Path>>(filepath: P) -> Result<bool> {
+    let filepath = filepath.as_ref();
+    let reader = BufReader::new(File::open(filepath)?);
+    let mut info: serde_json::Map<String, serde_json::Value> = serde_json::from_reader(reader)?;
+
+    let removed = BULKY_KEYS
+        .iter()
+        .filter(|key| info.remove(**key).is_some())
+        .count();
+    if removed == 0 {
+        return Ok(false);
+    }
+    info.insert(VERSION_KEY.into(), VERSION.into());
+
+    // replace ".json" with ".partial", which the info file patterns do not match either
+    let partial_filepath = filepath.with_extension("partial");
+    {
+        let mut writer = BufWriter::new(File::create(&partial_filepath)?);
+        serde_json::to_writer(&mut writer, &info)?;
+        writer.flush()?;
+    }
+
+    let backup_filepath = backup_filepath(filepath);
+    if !backup_filepath.exists() {
+        std::fs::rename(filepath, &backup_filepath)?;
+    }
+    std::fs::rename(&partial_filepath, filepath)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_strips_bulky_keys_and_keeps_a_backup() {
+        let directory = tempfile::tempdir().unwrap();
+        let filepath = directory
+            .path()
+            .join("20230519--dQw4w9WgXcQ--Episode.info.json");
+        let original = serde_json::json!({
+            "id": "dQw4w9WgXcQ",
+            "title": "Episode",
+            "thumbnail": "https://i.ytimg.com/vi/dQw4w9WgXcQ/maxresdefault.jpg",
+            "formats": [{"format_id": "18", "url": "https://example.com/video"}],
+            "heatmap": [{"start_time": 0.0, "end_time": 1.0, "value": 1.0}],
+        })
+        .to_string();
+        std::fs::write(&filepath, &original).unwrap();
+
+        assert!(needs_compaction(&filepath).unwrap());
+        assert!(compact(&filepath).unwrap());
+
+        let compacted: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&filepath).unwrap()).unwrap();
+        assert_eq!(compacted["title"], "Episode");
+        assert!(compacted["thumbnail"].is_string());
+        assert!(compacted.get("formats").is_none());
+        assert!(compacted.get("heatmap").is_none());
+        assert_eq!(compacted[VERSION_KEY], VERSION);
+
+        let backup = directory
+            .path()
+            .join("20230519--dQw4w9WgXcQ--Episode.info.orig.json");
+        assert_eq!(backup_filepath(&filepath), backup);
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), original);
+
+        // Compacted files are left alone, and the backup is not listed as an episode.
+        assert!(!needs_compaction(&filepath).unwrap());
+        assert!(!compact(&filepath).unwrap());
+        let episodes = smol::block_on(super::super::episode::available_episodes(directory.path()));
+        assert_eq!(episodes.unwrap().len(), 1);
+    }
+}
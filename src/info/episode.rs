@@ -2,12 +2,27 @@
 
 use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, NaiveDate, offset::Utc};
+use chrono::NaiveDate;
 use futures::stream::StreamExt;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use tracing::debug;
 
-use crate::Result;
+use crate::{Result, error::Error, probe, source};
+
+/// The enclosure of an episode, now shared by all the metadata sources.
+#[deprecated(note = "moved to `podfeed::source::Enclosure`")]
+pub type Enclosure = source::Enclosure;
+
+/// Extensions of the media files downloaded by yt-dlp, by order of preference.
+const MEDIA_EXTENSIONS: [&str; 5] = ["mp4", "m4a", "mp3", "opus", "ogg"];
+
+/// Relative difference between the yt-dlp and probed durations above which a warning is
+/// printed, provided it also exceeds [`DURATION_TOLERANCE_SECONDS`].
+const DURATION_TOLERANCE_RATIO: f64 = 0.05;
+
+/// Absolute difference between the yt-dlp and probed durations tolerated without warning.
+const DURATION_TOLERANCE_SECONDS: f64 = 5.0;
 
 /// Represents the info.json file of an episode.
 #[derive(Debug, Clone)]
@@ -22,18 +37,17 @@ pub struct InfoFile {
 
 impl InfoFile {
     /// Parse the associated `EpisodeInfo` and return it along with the enclosure.
-    pub async fn parse(&self) -> Result<(Info, Enclosure, PathBuf)> {
-        let content = smol::fs::read_to_string(&self.filepath).await?;
-        let ep_info: Info = serde_json::from_str(&content)?;
+    ///
+    /// The media file is probed for its type and duration. If it cannot be probed, the type is
+    /// guessed from its extension. Problems are reported in [`source::Enclosure::warnings`].
+    pub async fn parse(&self) -> Result<(Info, source::Enclosure, PathBuf)> {
+        let ep_info: Info = super::read(&self.filepath).await?;
 
         let video_filepath = self
-            .filepath
-            // remove ".json"
-            .with_extension("")
-            // replace ".info" with ".mp4"
-            .with_extension("mp4");
-
-        let video_filelength = smol::fs::metadata(&video_filepath).await?.len();
+            .media_filepath()
+            .await
+            .ok_or_else(|| Error::MissingMedia(self.filepath.clone()))?;
+        let enclosure = probe_enclosure(video_filepath, ep_info.duration_seconds).await?;
 
         let image_filepath = self
             .filepath
@@ -42,14 +56,75 @@ impl InfoFile {
             // replace ".info" with ".mp4"
             .with_extension("png");
 
-        let enclosure = Enclosure {
-            video_filepath,
-            video_filelength,
-            video_filetype: "mp4".into(),
-        };
-
         Ok((ep_info, enclosure, image_filepath))
     }
+
+    /// Return the filepath of the media file, with the first extension in
+    /// [`MEDIA_EXTENSIONS`] that exists, or `None` if none does.
+    pub async fn media_filepath(&self) -> Option<PathBuf> {
+        // remove ".json", then ".info"
+        let stem = self.filepath.with_extension("").with_extension("");
+        for extension in MEDIA_EXTENSIONS {
+            let filepath = stem.with_extension(extension);
+            if smol::fs::metadata(&filepath).await.is_ok() {
+                return Some(filepath);
+            }
+        }
+        None
+    }
+}
+
+/// Probe the media file at `video_filepath`, whose duration reported by its metadata is
+/// `expected_duration`, and return the enclosure.
+///
+/// If the file cannot be probed, its type is guessed from its extension. Problems are reported
+/// in [`source::Enclosure::warnings`].
+pub(crate) async fn probe_enclosure(
+    video_filepath: PathBuf,
+    expected_duration: Option<u32>,
+) -> Result<source::Enclosure> {
+    let video_filelength = smol::fs::metadata(&video_filepath).await?.len();
+
+    let probed = {
+        let video_filepath = video_filepath.clone();
+        smol::unblock(move || probe::probe(video_filepath)).await
+    };
+    let faststart = probed.as_ref().ok().and_then(|probed| probed.faststart);
+    let mut warnings = Vec::new();
+    let (video_filetype, duration_seconds) = match probed {
+        Ok(probed) => {
+            if let (Some(expected), Some(actual)) = (expected_duration, probed.duration_seconds) {
+                let expected = expected as f64;
+                let tolerance = DURATION_TOLERANCE_SECONDS.max(expected * DURATION_TOLERANCE_RATIO);
+                if (actual - expected).abs() > tolerance {
+                    warnings.push(format!(
+                        "{} lasts {actual:.0}s, but yt-dlp reports {expected:.0}s",
+                        video_filepath.display()
+                    ));
+                }
+            }
+            (
+                probed.mime_type.to_string(),
+                probed.duration_seconds.map(|d| d.round() as u32),
+            )
+        }
+        Err(e) => {
+            warnings.push(e.to_string());
+            (
+                probe::mime_type_from_extension(&video_filepath).into(),
+                None,
+            )
+        }
+    };
+
+    Ok(source::Enclosure {
+        video_filepath,
+        video_filelength,
+        video_filetype,
+        duration_seconds,
+        faststart,
+        warnings,
+    })
 }
 
 /// Return all episode files in `dirpath`.
@@ -73,6 +148,11 @@ pub async fn available_episodes<P: AsRef<Path>>(dirpath: P) -> Result<Vec<InfoFi
                 filepath: path,
             };
             episodes.push(episode);
+        } else if path.to_string_lossy().ends_with(".info.json") {
+            debug!(
+                file = %path.display(),
+                "skipping info file: name does not match `<date>--<id>--<title>.info.json`"
+            );
         }
     }
 
@@ -109,30 +189,74 @@ pub struct Info {
     #[serde(rename = "channel")]
     pub author: String,
 
-    /// Duration of the episode.
-    #[serde(rename = "duration")]
-    pub duration_seconds: u32,
+    /// Duration of the episode, if reported by yt-dlp.
+    #[serde(
+        rename = "duration",
+        default,
+        deserialize_with = "deserialize_duration"
+    )]
+    pub duration_seconds: Option<u32>,
+
+    /// Live status, e.g. `not_live` or `was_live`, if reported by yt-dlp.
+    #[serde(default)]
+    pub live_status: Option<String>,
+
+    /// Whether the episode was a live stream, if reported by yt-dlp.
+    #[serde(default)]
+    pub was_live: Option<bool>,
+
+    /// Availability, e.g. `public` or `subscriber_only`, if reported by yt-dlp.
+    #[serde(default)]
+    pub availability: Option<String>,
+
+    /// Tags of the video, if reported by yt-dlp.
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+
+    /// Minimum age of the audience, if reported by yt-dlp.
+    #[serde(default)]
+    pub age_limit: Option<u32>,
+
+    /// Language of the video, e.g. `en`, if reported by yt-dlp.
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
-impl Info {
-    pub(crate) fn pub_date(&self) -> DateTime<Utc> {
-        let naived_date = NaiveDate::parse_from_str(&self.upload_date, "%Y%m%d")
-            .unwrap()
-            .and_hms_opt(9, 10, 11)
-            .unwrap();
-        DateTime::<Utc>::from_naive_utc_and_offset(naived_date, Utc)
-    }
+/// Deserialize a duration in seconds, which yt-dlp may report as a float.
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u32>, D::Error> {
+    let seconds: Option<f64> = Option::deserialize(deserializer)?;
+    Ok(seconds.map(|seconds| seconds.round() as u32))
 }
 
-/// Represents the video file for an episode.
-#[derive(Debug, Clone)]
-pub struct Enclosure {
-    /// Path to the video file.
-    pub video_filepath: PathBuf,
-    /// File length of the video file.
-    pub video_filelength: u64,
-    /// File type of the video.
-    pub video_filetype: String,
+impl TryFrom<Info> for source::Episode {
+    type Error = Error;
+
+    fn try_from(info: Info) -> Result<Self> {
+        let upload_date = NaiveDate::parse_from_str(&info.upload_date, "%Y%m%d").map_err(|_| {
+            Error::MissingMetadata(format!(
+                "invalid upload date `{}` of episode {}",
+                info.upload_date, info.guid
+            ))
+        })?;
+        Ok(Self {
+            guid: info.guid,
+            upload_date,
+            playlist_index: info.playlist_index,
+            title: info.title,
+            link: Some(info.link),
+            description: info.description,
+            author: info.author,
+            duration_seconds: info.duration_seconds,
+            live_status: info.live_status,
+            was_live: info.was_live,
+            availability: info.availability,
+            keywords: info.tags.unwrap_or_default(),
+            age_limit: info.age_limit,
+            language: info.language,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -162,14 +286,30 @@ mod tests {
         assert_eq!(info.link, "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
         assert_eq!(info.description, "A test episode");
         assert_eq!(info.author, "Test Author");
-        assert_eq!(info.duration_seconds, 212);
+        assert_eq!(info.duration_seconds, Some(212));
+    }
+
+    #[test]
+    fn deserialize_episode_info_with_float_or_missing_duration() {
+        let float = sample_json().replace("212", "212.6");
+        let info: Info = serde_json::from_str(&float).unwrap();
+        assert_eq!(info.duration_seconds, Some(213));
+
+        let missing = sample_json().replace(r#""duration": 212"#, r#""duration": null"#);
+        let info: Info = serde_json::from_str(&missing).unwrap();
+        assert_eq!(info.duration_seconds, None);
     }
 
     #[test]
     fn pub_date_parses_yyyymmdd() {
         let info: Info = serde_json::from_str(sample_json()).unwrap();
-        let dt = info.pub_date();
+        let episode = source::Episode::try_from(info).unwrap();
+        let dt = episode.pub_date();
         assert_eq!(dt.format("%Y-%m-%d").to_string(), "2023-05-19");
+
+        let invalid = sample_json().replace("20230519", "NA");
+        let info: Info = serde_json::from_str(&invalid).unwrap();
+        assert!(source::Episode::try_from(info).is_err());
     }
 
     #[test]
@@ -238,13 +378,43 @@ mod tests {
 
         assert_eq!(enclosure.video_filepath, video_filepath);
         assert_eq!(enclosure.video_filelength, 7);
-        assert_eq!(enclosure.video_filetype, "mp4");
+        // Not a valid MP4 file: the type is guessed from the extension.
+        assert_eq!(enclosure.video_filetype, "video/mp4");
+        assert_eq!(enclosure.duration_seconds, None);
+        assert_eq!(enclosure.faststart, None);
+        assert_eq!(enclosure.warnings.len(), 1);
         assert_eq!(
             image_filepath,
             directory.path().join("20230519--dQw4w9WgXcQ--Episode.png")
         );
     }
 
+    #[test]
+    fn info_file_parse_probes_audio_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let info_filepath = directory
+            .path()
+            .join("20230519--dQw4w9WgXcQ--Episode.info.json");
+        let audio_filepath = directory.path().join("20230519--dQw4w9WgXcQ--Episode.mp3");
+        std::fs::write(&info_filepath, sample_json()).unwrap();
+        // One second of 128 kbit/s MPEG-1 Layer III audio.
+        let mut audio = vec![0xFF, 0xFB, 0x90, 0x00];
+        audio.resize(16_000, 0);
+        std::fs::write(&audio_filepath, audio).unwrap();
+
+        let info_file = InfoFile {
+            pub_date: NaiveDate::from_ymd_opt(2023, 5, 19).unwrap(),
+            youtube_id: "dQw4w9WgXcQ".into(),
+            filepath: info_filepath,
+        };
+        let (info, enclosure, _) = smol::block_on(info_file.parse()).unwrap();
+
+        assert_eq!(enclosure.video_filepath, audio_filepath);
+        assert_eq!(enclosure.video_filetype, "audio/mpeg");
+        assert_eq!(enclosure.duration_seconds, Some(1));
+        assert_eq!(info.duration_seconds, Some(212));
+    }
+
     #[test]
     fn episode_date_parsing_from_filename() {
         let date = NaiveDate::parse_from_str("20230519", "%Y%m%d").unwrap();
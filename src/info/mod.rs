@@ -1,4 +1,136 @@
 //! Handle .info.json files for episodes and channel.
 
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+use crate::{
+    Result,
+    error::Error,
+    settings::ChannelSettings,
+    source::{self, ChannelFile, EpisodeFile, MetadataSource},
+};
+
 pub mod channel;
+pub mod compact;
 pub mod episode;
+
+/// Deserialize the info file at `filepath`.
+///
+/// The file is streamed through a buffered reader, so the keys that are not deserialized,
+/// such as the lists of formats and thumbnails, are skipped without being allocated.
+pub(crate) async fn read<T: DeserializeOwned + Send + 'static>(filepath: &Path) -> Result<T> {
+    let filepath = filepath.to_path_buf();
+    smol::unblock(move || {
+        let reader = BufReader::new(File::open(filepath)?);
+        Ok(serde_json::from_reader(reader)?)
+    })
+    .await
+}
+
+/// The default metadata source: the `.info.json` files written by yt-dlp.
+#[derive(Debug, Clone, Default)]
+pub struct YtDlp {
+    /// Policy choosing among several playlist info files, from the `playlist` table of the
+    /// channel.
+    pub playlist: channel::Settings,
+}
+
+impl MetadataSource for YtDlp {
+    fn configure(&self, settings: &ChannelSettings) -> Option<Arc<dyn MetadataSource>> {
+        Some(Arc::new(YtDlp {
+            playlist: settings.playlist.clone(),
+        }))
+    }
+
+    fn discover_channel<'a>(
+        &'a self,
+        dirpath: &'a Path,
+    ) -> BoxFuture<'a, Result<Option<ChannelFile>>> {
+        Box::pin(async move {
+            match channel::resolve(dirpath, &self.playlist).await {
+                Err(Error::MissingChannelInfoFile(_)) => {
+                    debug!(
+                        dir = %dirpath.display(),
+                        "no channel info file: describing the channel with its episodes"
+                    );
+                    Ok(None)
+                }
+                result => result.map(Some),
+            }
+        })
+    }
+
+    fn discover_episodes<'a>(
+        &'a self,
+        dirpath: &'a Path,
+    ) -> BoxFuture<'a, Result<Vec<EpisodeFile>>> {
+        Box::pin(async move {
+            let infofiles = episode::available_episodes(dirpath).await?;
+            Ok(infofiles
+                .into_iter()
+                .zip(1..)
+                .map(|(infofile, position)| EpisodeFile {
+                    id: infofile.youtube_id,
+                    filepath: infofile.filepath,
+                    position,
+                    warnings: Vec::new(),
+                })
+                .collect())
+        })
+    }
+
+    fn parse_episode<'a>(
+        &'a self,
+        file: &'a EpisodeFile,
+    ) -> BoxFuture<'a, Result<Option<(source::Episode, source::Enclosure, PathBuf)>>> {
+        Box::pin(async move {
+            let infofile = episode::InfoFile {
+                pub_date: Default::default(),
+                youtube_id: file.id.clone(),
+                filepath: file.filepath.clone(),
+            };
+            if infofile.media_filepath().await.is_none() {
+                debug!(file = %file.filepath.display(), "skipping episode: no media file");
+                return Ok(None);
+            }
+            let (info, enclosure, image_filepath) = infofile.parse().await?;
+            Ok(Some((info.try_into()?, enclosure, image_filepath)))
+        })
+    }
+
+    fn parse_channel<'a>(
+        &'a self,
+        dirpath: &'a Path,
+        file: Option<&'a ChannelFile>,
+        episodes: &'a [&'a source::Episode],
+    ) -> BoxFuture<'a, Result<(source::Channel, PathBuf)>> {
+        Box::pin(async move {
+            let Some(file) = file else {
+                return channel::from_episodes(dirpath, episodes).await;
+            };
+            let infofile = channel::InfoFile {
+                youtube_id: String::new(),
+                filepath: file.filepath.clone(),
+            };
+            let (info, image_filepath) = infofile.parse().await?;
+            let mut channel: source::Channel = info.try_into()?;
+            for filepath in &file.merged {
+                let info: channel::Info = read(filepath).await?;
+                if !info.description.is_empty() && !channel.description.contains(&info.description)
+                {
+                    channel.description.push_str("\n\n");
+                    channel.description.push_str(&info.description);
+                }
+            }
+            Ok((channel, image_filepath))
+        })
+    }
+}
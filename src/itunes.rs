@@ -0,0 +1,236 @@
+//! iTunes tags mapped from the metadata of the episodes.
+//!
+//! yt-dlp reports more than the RSS fields need: tags, age limit, language. Each mapping can
+//! be turned off per channel in the `itunes` table:
+//!
+//! ```toml
+//! [itunes]
+//! keywords = false
+//!
+//! [channels."Cooking".itunes]
+//! explicit = false
+//! ```
+
+use serde::Deserialize;
+
+use crate::{rss, source};
+
+/// Minimum age limit of the episodes marked as explicit.
+pub const EXPLICIT_AGE_LIMIT: u32 = 18;
+
+/// Maximum length of `itunes:subtitle`, in characters.
+pub const SUBTITLE_MAX_CHARS: usize = 255;
+
+/// Maximum length of `itunes:summary`, in characters, as accepted by Apple Podcasts.
+pub const SUMMARY_MAX_CHARS: usize = 4000;
+
+/// iTunes tag settings of a channel, all enabled by default.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Map the tags of the episodes to `itunes:keywords`.
+    pub keywords: bool,
+    /// Mark the episodes with an age limit of 18 or more as `itunes:explicit`.
+    pub explicit: bool,
+    /// Use the language of the newest episode for channels without a language.
+    pub language: bool,
+    /// Copy the description of the episodes to `itunes:summary`.
+    pub summary: bool,
+    /// Copy the first line of the description of the episodes to `itunes:subtitle`.
+    pub subtitle: bool,
+    /// Copy the title of the episodes to `itunes:title`.
+    pub title: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            keywords: true,
+            explicit: true,
+            language: true,
+            summary: true,
+            subtitle: true,
+            title: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Fill the iTunes tags of the RSS `episode` converted from `source`.
+    pub fn apply(&self, source: &source::Episode, episode: &mut rss::episode::Episode) {
+        if self.explicit
+            && source
+                .age_limit
+                .is_some_and(|age_limit| age_limit >= EXPLICIT_AGE_LIMIT)
+        {
+            episode.explicit_content = "true".into();
+        }
+        if self.title {
+            episode.itunes_title = Some(source.title.clone());
+        }
+        if self.subtitle {
+            episode.subtitle = source
+                .description
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(|line| truncate(line, SUBTITLE_MAX_CHARS));
+        }
+        if self.summary && !source.description.trim().is_empty() {
+            episode.summary = Some(truncate(source.description.trim(), SUMMARY_MAX_CHARS));
+        }
+        if self.keywords {
+            episode.keywords = keywords(&source.keywords);
+        }
+    }
+
+    /// Set the language of `channel`, if it has none, to the language of the newest of its
+    /// `episodes` that reports one.
+    pub fn fill_language<'a>(
+        &self,
+        channel: &mut source::Channel,
+        episodes: impl IntoIterator<Item = &'a source::Episode>,
+    ) {
+        if !self.language || channel.language.is_some() {
+            return;
+        }
+        channel.language = episodes
+            .into_iter()
+            .filter(|episode| episode.language.is_some())
+            .max_by_key(|episode| episode.upload_date)
+            .and_then(|episode| episode.language.clone());
+    }
+}
+
+/// Return the comma-separated `tags`, trimmed and without duplicates, or `None` if there are
+/// none.
+fn keywords(tags: &[String]) -> Option<String> {
+    let mut keywords: Vec<&str> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !keywords.contains(&tag) {
+            keywords.push(tag);
+        }
+    }
+    (!keywords.is_empty()).then(|| keywords.join(","))
+}
+
+/// Return `text` truncated to `max_chars` characters, ending with an ellipsis if truncated.
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars.saturating_sub(1)) {
+        Some((end, _)) if text[end..].chars().count() > 1 => format!("{}…", &text[..end]),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn episode(age_limit: Option<u32>, tags: &[&str]) -> source::Episode {
+        source::Episode {
+            guid: "dQw4w9WgXcQ".into(),
+            upload_date: NaiveDate::from_ymd_opt(2023, 5, 19).unwrap(),
+            playlist_index: 1,
+            title: "Title".into(),
+            link: None,
+            description: "\nFirst line\nSecond line".into(),
+            author: "Author".into(),
+            duration_seconds: None,
+            live_status: None,
+            was_live: None,
+            availability: None,
+            keywords: tags.iter().map(|tag| tag.to_string()).collect(),
+            age_limit,
+            language: Some("fr".into()),
+        }
+    }
+
+    fn rss_episode() -> rss::episode::Episode {
+        rss::episode::Episode {
+            guid: rss::episode::Guid {
+                is_perma_link: Some(false),
+                value: "dQw4w9WgXcQ".into(),
+            },
+            pub_date: "Fri, 19 May 2023 09:10:11 +0000".into(),
+            title: "Title".into(),
+            link: "https://youtube.com/watch?v=dQw4w9WgXcQ".into(),
+            description: "First line".into(),
+            enclosure: rss::episode::Enclosure {
+                file_url: "https://cdn.example.com/episode.mp4".into(),
+                file_length: "3".into(),
+                file_type: "video/mp4".into(),
+            },
+            author: "Author".into(),
+            image: rss::episode::Image {
+                file_url: "https://cdn.example.com/episode.png".into(),
+            },
+            duration: None,
+            explicit_content: "false".into(),
+            itunes_title: None,
+            subtitle: None,
+            summary: None,
+            keywords: None,
+        }
+    }
+
+    #[test]
+    fn episode_tags_are_mapped() {
+        let mut target = rss_episode();
+        Settings::default().apply(
+            &episode(Some(18), &["cooking", " baking ", "cooking"]),
+            &mut target,
+        );
+        assert_eq!(target.explicit_content, "true");
+        assert_eq!(target.itunes_title.as_deref(), Some("Title"));
+        assert_eq!(target.subtitle.as_deref(), Some("First line"));
+        assert_eq!(target.summary.as_deref(), Some("First line\nSecond line"));
+        assert_eq!(target.keywords.as_deref(), Some("cooking,baking"));
+
+        let mut target = rss_episode();
+        Settings::default().apply(&episode(Some(13), &[]), &mut target);
+        assert_eq!(target.explicit_content, "false");
+        assert_eq!(target.keywords, None);
+
+        let settings: Settings = toml::from_str("explicit = false\nsummary = false").unwrap();
+        let mut target = rss_episode();
+        settings.apply(&episode(Some(18), &["cooking"]), &mut target);
+        assert_eq!(target.explicit_content, "false");
+        assert_eq!(target.summary, None);
+        assert_eq!(target.keywords.as_deref(), Some("cooking"));
+    }
+
+    #[test]
+    fn channel_language_falls_back_to_the_episodes() {
+        let mut channel = source::Channel {
+            upload_date: NaiveDate::from_ymd_opt(2023, 5, 19).unwrap(),
+            title: "Channel".into(),
+            description: String::new(),
+            link: None,
+            author: "Author".into(),
+            language: None,
+        };
+        let episode = episode(None, &[]);
+        Settings::default().fill_language(&mut channel, [&episode]);
+        assert_eq!(channel.language(), "fr");
+
+        channel.language = Some("de".into());
+        Settings::default().fill_language(&mut channel, [&episode]);
+        assert_eq!(channel.language(), "de");
+
+        channel.language = None;
+        let settings = Settings {
+            language: false,
+            ..Settings::default()
+        };
+        settings.fill_language(&mut channel, [&episode]);
+        assert_eq!(channel.language(), "en");
+    }
+
+    #[test]
+    fn truncate_counts_characters() {
+        assert_eq!(truncate("héllo", 5), "héllo");
+        assert_eq!(truncate("héllo wörld", 5), "héll…");
+    }
+}
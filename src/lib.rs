@@ -1,11 +1,30 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+pub mod access;
+pub mod aggregate;
+pub mod artwork;
+pub mod category;
 pub mod config;
 pub mod convert;
 pub mod error;
+pub mod faststart;
+pub mod filter;
+pub mod generator;
+pub mod guid;
+pub mod history;
+pub mod hooks;
 pub mod info;
+pub mod itunes;
+pub mod local;
+pub mod probe;
+pub mod report;
 pub mod rss;
+pub mod settings;
+pub mod source;
+pub mod split;
+pub mod tags;
+pub mod writer;
 
 /// Result type for this crate.
 pub type Result<T> = std::result::Result<T, error::Error>;
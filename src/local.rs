@@ -0,0 +1,423 @@
+//! Channel directories of local audio files, without yt-dlp metadata.
+//!
+//! Folders of ripped audiobooks or lectures are read from the tags of their MP3 and M4A files:
+//! title, artist, album, date, track number and embedded cover art. The folder name supplies
+//! the title of the channel, and a `folder.jpg` or `cover.jpg` image its artwork.
+//!
+//! Embedded cover art is extracted next to each audio file, as `<stem>.jpg` or `<stem>.png`,
+//! unless such an image already exists. The stem identifies the episode, so of several audio
+//! files with the same stem only the first one by filename is used.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use futures::{future::BoxFuture, stream::StreamExt};
+use tracing::debug;
+
+use crate::{
+    Result,
+    error::Error,
+    info,
+    source::{self, ChannelFile, Enclosure, EpisodeFile, MetadataSource},
+    tags::{self, Tags},
+};
+
+/// Extensions of the audio files, lowercase.
+const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "m4a", "m4b"];
+
+/// Names of the image files supplying the channel artwork, by order of preference.
+const FOLDER_IMAGES: [&str; 4] = ["folder.jpg", "folder.png", "cover.jpg", "cover.png"];
+
+/// Extensions of the episode images, by order of preference.
+const IMAGE_EXTENSIONS: [&str; 2] = ["jpg", "png"];
+
+/// An audio file of a channel directory, with its tags.
+#[derive(Debug, Clone)]
+pub struct AudioFile {
+    /// Stem of the filename, which identifies the episode.
+    pub id: String,
+    /// Filepath of the audio file.
+    pub filepath: PathBuf,
+    /// Position of the file in the directory, by filename, starting at 1.
+    pub position: u32,
+    /// Tags of the file, without the cover art.
+    pub tags: Tags,
+    /// Image of the episode, if the file has embedded cover art or an image next to it.
+    pub image_filepath: Option<PathBuf>,
+    /// Problem found while reading the tags.
+    pub warning: Option<String>,
+}
+
+impl AudioFile {
+    /// Read the tags of the audio file at `filepath`, whose position in the directory is
+    /// `position`, extracting its embedded cover art unless an image is next to it.
+    pub async fn read(filepath: PathBuf, position: u32) -> Result<Self> {
+        let read = {
+            let filepath = filepath.clone();
+            smol::unblock(move || tags::read(filepath)).await
+        };
+        let (mut tags, warning) = match read {
+            Ok(tags) => (tags, None),
+            Err(e) => (Tags::default(), Some(e.to_string())),
+        };
+
+        let mut image_filepath = None;
+        for extension in IMAGE_EXTENSIONS {
+            let candidate = filepath.with_extension(extension);
+            if smol::fs::metadata(&candidate).await.is_ok() {
+                image_filepath = Some(candidate);
+                break;
+            }
+        }
+        if let Some(cover) = tags.cover.take()
+            && image_filepath.is_none()
+        {
+            let candidate = filepath.with_extension(cover.extension);
+            debug!(file = %candidate.display(), "extracting embedded cover art");
+            smol::fs::write(&candidate, cover.data).await?;
+            image_filepath = Some(candidate);
+        }
+
+        Ok(AudioFile {
+            id: filepath
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            filepath,
+            position,
+            tags,
+            image_filepath,
+            warning,
+        })
+    }
+
+    /// Probe the audio file, and return its metadata along with the enclosure and the episode
+    /// image, which defaults to the channel artwork.
+    ///
+    /// The author defaults to the name of the folder, and the link to the public URL of the
+    /// audio file.
+    pub async fn parse(&self) -> Result<(source::Episode, Enclosure, PathBuf)> {
+        let mut enclosure = info::episode::probe_enclosure(self.filepath.clone(), None).await?;
+        enclosure.warnings.extend(self.warning.clone());
+
+        let upload_date = match self.tags.date() {
+            Some(date) => date,
+            None => {
+                let modified = smol::fs::metadata(&self.filepath).await?.modified()?;
+                DateTime::<Local>::from(modified).date_naive()
+            }
+        };
+        let dirpath = self.filepath.parent().unwrap_or(Path::new("."));
+        let title = self.tags.title.clone().unwrap_or_else(|| self.id.clone());
+        let episode = source::Episode {
+            guid: self.id.clone(),
+            upload_date,
+            playlist_index: self.tags.track.unwrap_or(self.position),
+            description: self.tags.album.clone().unwrap_or_else(|| title.clone()),
+            title,
+            link: None,
+            author: self
+                .tags
+                .artist
+                .clone()
+                .unwrap_or_else(|| folder_name(dirpath)),
+            duration_seconds: None,
+            live_status: None,
+            was_live: None,
+            availability: None,
+            keywords: Vec::new(),
+            age_limit: None,
+            language: None,
+        };
+
+        let image_filepath = match &self.image_filepath {
+            Some(image_filepath) => image_filepath.clone(),
+            None => folder_image(dirpath).await?.ok_or_else(|| {
+                Error::MissingMetadata(format!("no cover art for `{}`", self.filepath.display()))
+            })?,
+        };
+        Ok((episode, enclosure, image_filepath))
+    }
+}
+
+/// The metadata source of local audio files, read from their tags.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioFiles;
+
+impl MetadataSource for AudioFiles {
+    fn discover_channel<'a>(
+        &'a self,
+        _dirpath: &'a Path,
+    ) -> BoxFuture<'a, Result<Option<ChannelFile>>> {
+        Box::pin(async move { Ok(None) })
+    }
+
+    fn discover_episodes<'a>(
+        &'a self,
+        dirpath: &'a Path,
+    ) -> BoxFuture<'a, Result<Vec<EpisodeFile>>> {
+        Box::pin(available_episodes(dirpath))
+    }
+
+    fn parse_episode<'a>(
+        &'a self,
+        file: &'a EpisodeFile,
+    ) -> BoxFuture<'a, Result<Option<(source::Episode, Enclosure, PathBuf)>>> {
+        Box::pin(async move {
+            let audio_file = AudioFile::read(file.filepath.clone(), file.position).await?;
+            Ok(Some(audio_file.parse().await?))
+        })
+    }
+
+    fn parse_channel<'a>(
+        &'a self,
+        dirpath: &'a Path,
+        _file: Option<&'a ChannelFile>,
+        episodes: &'a [&'a source::Episode],
+    ) -> BoxFuture<'a, Result<(source::Channel, PathBuf)>> {
+        Box::pin(channel(dirpath, episodes))
+    }
+}
+
+/// Return the audio files in `dirpath`, ordered by filename, without reading their tags.
+///
+/// The stem of the filename identifies the episode: of several files with the same stem, e.g.
+/// `Part 1.m4a` and `Part 1.mp3`, the first one is kept with a warning about the others.
+pub async fn available_episodes<P: AsRef<Path>>(dirpath: P) -> Result<Vec<EpisodeFile>> {
+    let mut filepaths = Vec::new();
+    let mut entries = smol::fs::read_dir(dirpath.as_ref()).await?;
+    while let Some(entry) = entries.next().await {
+        let path = entry?.path();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        if extension.is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.as_str())) {
+            filepaths.push(path);
+        }
+    }
+    filepaths.sort();
+
+    let mut episode_files: Vec<EpisodeFile> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    let mut position = 0;
+    for filepath in filepaths {
+        let id = filepath
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        if let Some(&index) = indices.get(&id) {
+            let kept = &mut episode_files[index];
+            kept.warnings.push(format!(
+                "{} is skipped: {} has the same name, which identifies the episode",
+                filepath.display(),
+                kept.filepath.display()
+            ));
+            continue;
+        }
+        position += 1;
+        indices.insert(id.clone(), episode_files.len());
+        episode_files.push(EpisodeFile {
+            id,
+            filepath,
+            position,
+            warnings: Vec::new(),
+        });
+    }
+    Ok(episode_files)
+}
+
+/// Return the metadata of the channel directory `dirpath` with the `episodes`, along with the
+/// channel artwork.
+///
+/// The title is the folder name, and the author and description the most frequent artist and
+/// album.
+pub async fn channel<P: AsRef<Path>>(
+    dirpath: P,
+    episodes: &[&source::Episode],
+) -> Result<(source::Channel, PathBuf)> {
+    let dirpath = dirpath.as_ref();
+    let title = folder_name(dirpath);
+    let image_filepath = folder_image(dirpath).await?.ok_or_else(|| {
+        Error::MissingMetadata(format!(
+            "no folder.jpg nor cover art in `{}`",
+            dirpath.display()
+        ))
+    })?;
+
+    let upload_date = episodes
+        .iter()
+        .map(|episode| episode.upload_date)
+        .max()
+        .unwrap_or_else(|| Local::now().date_naive());
+    let channel = source::Channel {
+        upload_date,
+        description: most_frequent(episodes.iter().map(|episode| &episode.description))
+            .unwrap_or_else(|| title.clone()),
+        author: most_frequent(episodes.iter().map(|episode| &episode.author))
+            .unwrap_or_else(|| title.clone()),
+        title,
+        link: None,
+        language: None,
+    };
+    Ok((channel, image_filepath))
+}
+
+/// Return the name of the folder `dirpath`.
+fn folder_name(dirpath: &Path) -> String {
+    dirpath
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Return the folder image of `dirpath`, or else the first episode image, if any.
+///
+/// Episodes are read one at a time, so the embedded cover art of the following episodes may
+/// not be extracted yet: without any image, the cover art of the first audio file with one is
+/// extracted.
+async fn folder_image(dirpath: &Path) -> Result<Option<PathBuf>> {
+    for name in FOLDER_IMAGES {
+        let filepath = dirpath.join(name);
+        if smol::fs::metadata(&filepath).await.is_ok() {
+            return Ok(Some(filepath));
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut entries = smol::fs::read_dir(dirpath).await?;
+    while let Some(entry) = entries.next().await {
+        let path = entry?.path();
+        let is_image = path
+            .extension()
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_string_lossy().as_ref()));
+        let has_audio = AUDIO_EXTENSIONS
+            .iter()
+            .any(|extension| path.with_extension(extension).exists());
+        if is_image && has_audio {
+            images.push(path);
+        }
+    }
+    images.sort();
+    if let Some(image) = images.into_iter().next() {
+        return Ok(Some(image));
+    }
+
+    for file in available_episodes(dirpath).await? {
+        let audio_file = AudioFile::read(file.filepath, file.position).await?;
+        if audio_file.image_filepath.is_some() {
+            return Ok(audio_file.image_filepath);
+        }
+    }
+    Ok(None)
+}
+
+/// Return the most frequent of `values`, the first one on ties.
+fn most_frequent<'a>(values: impl Iterator<Item = &'a String>) -> Option<String> {
+    let mut counts: BTreeMap<&String, (usize, usize)> = BTreeMap::new();
+    for (index, value) in values.enumerate() {
+        counts.entry(value).or_insert((0, index)).0 += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, (count, first))| (*count, std::cmp::Reverse(*first)))
+        .map(|(value, _)| value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::id3::tests::{frame, tag};
+
+    #[test]
+    fn audio_files_are_read_from_their_tags() {
+        let directory = tempfile::tempdir().unwrap();
+        let dirpath = directory.path().join("Lectures");
+        std::fs::create_dir(&dirpath).unwrap();
+
+        let mut cover = b"\x00image/png\x00\x03\x00".to_vec();
+        cover.extend(b"\x89PNG\r\n\x1a\n");
+        let tagged = tag(&[
+            frame(b"TIT2", b"\x00Introduction"),
+            frame(b"TPE1", b"\x00Lecturer"),
+            frame(b"TALB", b"\x00Physics"),
+            frame(b"TDRC", b"\x002021-03-05"),
+            frame(b"TRCK", b"\x002/10"),
+            frame(b"APIC", &cover),
+        ]);
+        std::fs::write(dirpath.join("b.mp3"), tagged).unwrap();
+        std::fs::write(dirpath.join("a.mp3"), b"untagged").unwrap();
+        std::fs::write(dirpath.join("notes.txt"), b"").unwrap();
+
+        // Discovery only lists the files, whose tags are read once, when parsed.
+        let files = smol::block_on(available_episodes(&dirpath)).unwrap();
+        let ids: Vec<&str> = files.iter().map(|file| file.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert!(!dirpath.join("b.png").exists());
+        let read = |file: &EpisodeFile| {
+            smol::block_on(AudioFile::read(file.filepath.clone(), file.position)).unwrap()
+        };
+
+        // Untagged files fall back to the filename, the folder and the other cover art.
+        let untagged_file = read(&files[0]);
+        assert!(untagged_file.warning.is_none());
+        let (untagged, _, image) = smol::block_on(untagged_file.parse()).unwrap();
+        assert_eq!(untagged.title, "a");
+        assert_eq!(untagged.author, "Lectures");
+        assert_eq!(untagged.playlist_index, 1);
+        assert_eq!(image, dirpath.join("b.png"));
+        assert!(dirpath.join("b.png").exists());
+
+        let tagged_file = read(&files[1]);
+        assert_eq!(tagged_file.tags.cover, None);
+        assert_eq!(tagged_file.image_filepath, Some(dirpath.join("b.png")));
+        let (info, enclosure, image) = smol::block_on(tagged_file.parse()).unwrap();
+        assert_eq!(info.guid, "b");
+        assert_eq!(info.title, "Introduction");
+        assert_eq!(info.author, "Lecturer");
+        assert_eq!(info.description, "Physics");
+        assert_eq!(info.upload_date.to_string(), "2021-03-05");
+        assert_eq!(info.playlist_index, 2);
+        assert_eq!(enclosure.video_filetype, "audio/mpeg");
+        assert_eq!(image, dirpath.join("b.png"));
+
+        let (channel, image) = smol::block_on(channel(&dirpath, &[&info, &untagged])).unwrap();
+        assert_eq!(channel.title, "Lectures");
+        assert_eq!(channel.author, "Lecturer");
+        assert!(channel.upload_date >= info.upload_date);
+        assert_eq!(image, dirpath.join("b.png"));
+
+        std::fs::write(dirpath.join("folder.jpg"), b"").unwrap();
+        let (_, image) = smol::block_on(super::channel(&dirpath, &[])).unwrap();
+        assert_eq!(image, dirpath.join("folder.jpg"));
+    }
+
+    #[test]
+    fn files_with_the_same_stem_are_skipped_with_a_warning() {
+        let directory = tempfile::tempdir().unwrap();
+        for filename in ["Part 1.m4a", "Part 1.mp3", "Part 2.mp3"] {
+            std::fs::write(directory.path().join(filename), b"").unwrap();
+        }
+
+        let files = smol::block_on(available_episodes(directory.path())).unwrap();
+        let ids: Vec<&str> = files.iter().map(|file| file.id.as_str()).collect();
+        assert_eq!(ids, ["Part 1", "Part 2"]);
+        assert_eq!(files[0].filepath, directory.path().join("Part 1.m4a"));
+        assert_eq!(files[1].position, 2);
+        assert_eq!(files[0].warnings.len(), 1);
+        assert!(files[0].warnings[0].contains("Part 1.mp3 is skipped"));
+        assert!(files[1].warnings.is_empty());
+    }
+
+    #[test]
+    fn most_frequent_prefers_the_first_value_on_ties() {
+        let values = ["b", "a", "a", "b", "c"].map(String::from);
+        assert_eq!(most_frequent(values.iter()), Some("b".into()));
+        assert_eq!(most_frequent([].iter()), None);
+    }
+}
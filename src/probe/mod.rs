@@ -0,0 +1,120 @@
+//! Probe media files for their duration and stream kind.
+//!
+//! Supports MP4/M4A (`moov` atom), MP3 (Xing/Info and VBRI headers, or constant bitrate
+//! estimate) and Ogg (Opus, Vorbis, Theora) files, identified by their content rather than
+//! their extension.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{Result, error::Error};
+
+pub mod mp3;
+pub mod mp4;
+pub mod ogg;
+
+/// Kind of the streams found in a media file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Audio only.
+    Audio,
+    /// At least one video stream.
+    Video,
+}
+
+/// Properties of a media file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    /// Duration of the media, if known.
+    pub duration_seconds: Option<f64>,
+    /// Kind of the streams in the file.
+    pub kind: Kind,
+    /// MIME type of the file, e.g. `audio/mpeg` or `video/mp4`.
+    pub mime_type: &'static str,
+    /// For MP4 files, whether the `moov` box precedes the media data, which lets clients
+    /// start playing before the whole file is downloaded.
+    pub faststart: Option<bool>,
+}
+
+/// Probe the media file at `filepath`.
+pub fn probe<P: AsRef<Path>>(filepath: P) -> Result<Probe> {
+    let filepath = filepath.as_ref();
+    let mut reader = BufReader::new(File::open(filepath)?);
+
+    let mut magic = [0_u8; 12];
+    let read = read_up_to(&mut reader, &mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+    let magic = &magic[..read];
+
+    let probe = if magic.len() >= 8 && mp4::is_box_type(&magic[4..8]) {
+        mp4::probe(&mut reader)
+    } else if magic.starts_with(b"OggS") {
+        ogg::probe(&mut reader)
+    } else if magic.starts_with(b"ID3") || mp3::is_frame_sync(magic) {
+        mp3::probe(&mut reader)
+    } else {
+        Err(invalid("unknown media format"))
+    };
+
+    probe.map_err(|e| match e {
+        Error::Probe(_, reason) => Error::Probe(filepath.into(), reason),
+        e => e,
+    })
+}
+
+/// Return the MIME type commonly associated with the extension of `filepath`.
+pub fn mime_type_from_extension<P: AsRef<Path>>(filepath: P) -> &'static str {
+    let extension = filepath
+        .as_ref()
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("m4a" | "m4b") => "audio/mp4",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg" | "opus") => "audio/ogg",
+        _ => "video/mp4",
+    }
+}
+
+/// Return a probe error; the filepath is filled in by [`probe`].
+fn invalid(reason: &str) -> Error {
+    Error::Probe(Default::default(), reason.into())
+}
+
+/// Fill `buf` as much as possible, and return the number of bytes read.
+pub(crate) fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_rejects_unknown_formats() {
+        let directory = tempfile::tempdir().unwrap();
+        let filepath = directory.path().join("episode.mp4");
+        std::fs::write(&filepath, [0_u8; 7]).unwrap();
+
+        let error = probe(&filepath).unwrap_err();
+        assert!(matches!(error, Error::Probe(path, _) if path == filepath));
+    }
+
+    #[test]
+    fn mime_type_from_extension_defaults_to_mp4_video() {
+        assert_eq!(mime_type_from_extension("a/b.MP3"), "audio/mpeg");
+        assert_eq!(mime_type_from_extension("a/b.m4a"), "audio/mp4");
+        assert_eq!(mime_type_from_extension("a/b.opus"), "audio/ogg");
+        assert_eq!(mime_type_from_extension("a/b.mp4"), "video/mp4");
+    }
+}
@@ -0,0 +1,264 @@
+//! MP3 files (MPEG-1/2/2.5 audio).
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{Kind, Probe, invalid, read_up_to};
+use crate::Result;
+
+/// Number of bytes searched for the first frame after the ID3v2 tag.
+const SYNC_SEARCH_SIZE: usize = 64 * 1024;
+
+/// Bitrates in kbit/s, by table and bitrate index.
+const BITRATES: [[u32; 15]; 5] = [
+    // MPEG-1 Layer I
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    // MPEG-1 Layer II
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    // MPEG-1 Layer III
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    // MPEG-2/2.5 Layer I
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    // MPEG-2/2.5 Layer II & III
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+/// Return `true` if `bytes` starts with an MPEG audio frame sync.
+pub(crate) fn is_frame_sync(bytes: &[u8]) -> bool {
+    bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0
+}
+
+/// Decoded MPEG audio frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    /// `true` for MPEG-1, `false` for MPEG-2 and MPEG-2.5.
+    mpeg1: bool,
+    /// Layer, from 1 to 3.
+    layer: u8,
+    /// Bitrate in kbit/s.
+    bitrate: u32,
+    /// Sample rate in Hz.
+    sample_rate: u32,
+    /// Single channel.
+    mono: bool,
+}
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 || !is_frame_sync(bytes) {
+            return None;
+        }
+        let (mpeg1, rates) = match (bytes[1] >> 3) & 0b11 {
+            0 => (false, [11_025, 12_000, 8_000]),
+            2 => (false, [22_050, 24_000, 16_000]),
+            3 => (true, [44_100, 48_000, 32_000]),
+            _ => return None,
+        };
+        let layer = match (bytes[1] >> 1) & 0b11 {
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            _ => return None,
+        };
+        let table = match (mpeg1, layer) {
+            (true, layer) => layer as usize - 1,
+            (false, 1) => 3,
+            (false, _) => 4,
+        };
+        let bitrate = *BITRATES[table].get((bytes[2] >> 4) as usize)?;
+        let sample_rate = *rates.get(((bytes[2] >> 2) & 0b11) as usize)?;
+        if bitrate == 0 {
+            // Free format is not supported.
+            return None;
+        }
+        Some(Self {
+            mpeg1,
+            layer,
+            bitrate,
+            sample_rate,
+            mono: bytes[3] >> 6 == 0b11,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.mpeg1) {
+            (1, _) => 384,
+            (3, false) => 576,
+            _ => 1152,
+        }
+    }
+
+    /// Size of the Layer III side information, which precedes the Xing header.
+    fn side_info_size(&self) -> usize {
+        match (self.mpeg1, self.mono) {
+            (true, true) => 17,
+            (true, false) => 32,
+            (false, true) => 9,
+            (false, false) => 17,
+        }
+    }
+}
+
+/// Return the size of the ID3v2 tag at the start of `header`, or 0.
+fn id3v2_size(header: &[u8]) -> u64 {
+    if header.len() < 10 || !header.starts_with(b"ID3") {
+        return 0;
+    }
+    let size = header[6..10]
+        .iter()
+        .fold(0_u64, |size, b| (size << 7) | (*b & 0x7F) as u64);
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+/// Return the number of frames announced by a Xing/Info or VBRI header in `frame`.
+fn vbr_frame_count(frame: &[u8], header: &FrameHeader) -> Option<u32> {
+    let be_u32 = |offset: usize| {
+        frame
+            .get(offset..offset + 4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+    };
+
+    let xing = 4 + header.side_info_size();
+    if matches!(frame.get(xing..xing + 4), Some(b"Xing" | b"Info")) {
+        let flags = be_u32(xing + 4)?;
+        return if flags & 1 != 0 {
+            be_u32(xing + 8)
+        } else {
+            None
+        };
+    }
+
+    let vbri = 4 + 32;
+    if frame.get(vbri..vbri + 4) == Some(b"VBRI") {
+        return be_u32(vbri + 14);
+    }
+
+    None
+}
+
+/// Probe an MP3 file.
+pub fn probe<R: Read + Seek>(reader: &mut R) -> Result<Probe> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut tag_header = [0_u8; 10];
+    let read = read_up_to(reader, &mut tag_header)?;
+    let tag_size = id3v2_size(&tag_header[..read]);
+
+    reader.seek(SeekFrom::Start(tag_size))?;
+    let mut buf = vec![0_u8; SYNC_SEARCH_SIZE];
+    let read = read_up_to(reader, &mut buf)?;
+    buf.truncate(read);
+
+    let (position, header) = (0..buf.len())
+        .find_map(|i| FrameHeader::parse(&buf[i..]).map(|header| (i, header)))
+        .ok_or_else(|| invalid("no MPEG audio frame found"))?;
+
+    let duration_seconds = match vbr_frame_count(&buf[position..], &header) {
+        Some(frames) => {
+            frames as f64 * header.samples_per_frame() as f64 / header.sample_rate as f64
+        }
+        None => {
+            // Constant bitrate: estimate from the size of the audio data.
+            let audio_size = file_size - tag_size - position as u64;
+            audio_size as f64 * 8.0 / (header.bitrate as f64 * 1000.0)
+        }
+    };
+
+    Ok(Probe {
+        duration_seconds: Some(duration_seconds),
+        kind: Kind::Audio,
+        mime_type: "audio/mpeg",
+        faststart: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// MPEG-1 Layer III, 128 kbit/s, 44.1 kHz, stereo.
+    const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+
+    fn id3v2_tag(payload_size: usize) -> Vec<u8> {
+        let mut tag = b"ID3\x04\x00\x00".to_vec();
+        let size = payload_size as u32;
+        tag.extend([(size >> 21) as u8 & 0x7F, (size >> 14) as u8 & 0x7F]);
+        tag.extend([(size >> 7) as u8 & 0x7F, size as u8 & 0x7F]);
+        tag.resize(10 + payload_size, 0);
+        tag
+    }
+
+    #[test]
+    fn frame_header_parses_common_mp3() {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        assert!(header.mpeg1);
+        assert_eq!(header.layer, 3);
+        assert_eq!(header.bitrate, 128);
+        assert_eq!(header.sample_rate, 44_100);
+        assert_eq!(header.samples_per_frame(), 1152);
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x00]).is_none());
+    }
+
+    #[test]
+    fn probe_reads_xing_frame_count_after_id3v2_tag() {
+        let mut content = id3v2_tag(300);
+        let mut frame = HEADER.to_vec();
+        frame.resize(4 + 32, 0);
+        frame.extend(b"Xing");
+        frame.extend(1_u32.to_be_bytes());
+        frame.extend(1000_u32.to_be_bytes());
+        frame.resize(417, 0);
+        content.extend(frame);
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        let expected = 1000.0 * 1152.0 / 44_100.0;
+        assert!((probe.duration_seconds.unwrap() - expected).abs() < 1e-9);
+        assert_eq!(probe.kind, Kind::Audio);
+        assert_eq!(probe.mime_type, "audio/mpeg");
+    }
+
+    #[test]
+    fn probe_reads_vbri_frame_count() {
+        let mut frame = HEADER.to_vec();
+        frame.resize(4 + 32, 0);
+        frame.extend(b"VBRI");
+        frame.extend([0, 1, 0, 0, 0, 75]);
+        frame.extend(0_u32.to_be_bytes());
+        frame.extend(441_u32.to_be_bytes());
+        frame.resize(417, 0);
+
+        let probe = probe(&mut Cursor::new(frame)).unwrap();
+
+        let expected = 441.0 * 1152.0 / 44_100.0;
+        assert!((probe.duration_seconds.unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn probe_estimates_constant_bitrate_duration() {
+        // 16000 bytes of 128 kbit/s audio last one second.
+        let mut content = HEADER.to_vec();
+        content.resize(16_000, 0);
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        assert_eq!(probe.duration_seconds, Some(1.0));
+    }
+
+    #[test]
+    fn probe_rejects_files_without_frames() {
+        let content = id3v2_tag(20);
+        assert!(probe(&mut Cursor::new(content)).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+//! MP4/M4A files (ISO base media file format).
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use super::{Kind, Probe, invalid, read_up_to};
+use crate::Result;
+
+/// Largest `moov` box read in memory.
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Types of the boxes that may appear at the top level of an MP4 file.
+const TOP_LEVEL_TYPES: [&[u8; 4]; 12] = [
+    b"ftyp", b"moov", b"mdat", b"free", b"skip", b"wide", b"pdin", b"styp", b"sidx", b"moof",
+    b"meta", b"uuid",
+];
+
+/// Position of a box in an MP4 file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoxHeader {
+    /// Four-character type of the box, e.g. `moov`.
+    pub box_type: [u8; 4],
+    /// Offset of the box from the start of the file.
+    pub offset: u64,
+    /// Size of the box header, 8 or 16 bytes.
+    pub header_size: u64,
+    /// Size of the box, header included.
+    pub size: u64,
+}
+
+/// Return `true` if `bytes` is the type of a top-level MP4 box.
+pub(crate) fn is_box_type(bytes: &[u8]) -> bool {
+    TOP_LEVEL_TYPES.iter().any(|t| t.as_slice() == bytes)
+}
+
+/// Return the top-level boxes of an MP4 file.
+pub fn top_level_boxes<R: Read + Seek>(reader: &mut R) -> Result<Vec<BoxHeader>> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+
+    while offset < file_size {
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut header = [0_u8; 16];
+        let read = read_up_to(reader, &mut header)?;
+        if read < 8 {
+            return Err(invalid("truncated box header"));
+        }
+
+        let box_type: [u8; 4] = header[4..8].try_into().unwrap();
+        let (header_size, size) = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            0 => (8, file_size - offset),
+            1 if read == 16 => (16, u64::from_be_bytes(header[8..16].try_into().unwrap())),
+            1 => return Err(invalid("truncated box header")),
+            size => (8, size as u64),
+        };
+        if size < header_size || offset.checked_add(size).is_none_or(|end| end > file_size) {
+            return Err(invalid("invalid box size"));
+        }
+
+        boxes.push(BoxHeader {
+            box_type,
+            offset,
+            header_size,
+            size,
+        });
+        offset += size;
+    }
+
+    Ok(boxes)
+}
+
+/// Return whether the `moov` box precedes the first `mdat` box, or `None` if either is
+/// missing.
+pub fn is_faststart(boxes: &[BoxHeader]) -> Option<bool> {
+    let moov = boxes.iter().find(|b| &b.box_type == b"moov")?;
+    let mdat = boxes.iter().find(|b| &b.box_type == b"mdat")?;
+    Some(moov.offset < mdat.offset)
+}
+
+/// Read the payload of the `moov` box.
+pub(crate) fn read_moov<R: Read + Seek>(reader: &mut R, moov: &BoxHeader) -> Result<Vec<u8>> {
+    let payload_size = moov.size - moov.header_size;
+    if payload_size > MAX_MOOV_SIZE {
+        return Err(invalid("moov box too large"));
+    }
+    reader.seek(SeekFrom::Start(moov.offset + moov.header_size))?;
+    let mut payload = vec![0_u8; payload_size as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Return the child boxes in `payload`, as `(type, payload)` pairs.
+pub(crate) fn children(payload: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    Ok(child_ranges(payload)?
+        .into_iter()
+        .map(|(box_type, range)| (box_type, &payload[range]))
+        .collect())
+}
+
+/// Return the child boxes in `payload`, as `(type, payload range)` pairs.
+pub(crate) fn child_ranges(payload: &[u8]) -> Result<Vec<([u8; 4], Range<usize>)>> {
+    let mut children = Vec::new();
+    let mut offset = 0;
+    while offset < payload.len() {
+        let remaining = &payload[offset..];
+        if remaining.len() < 8 {
+            return Err(invalid("truncated box header"));
+        }
+        let box_type: [u8; 4] = remaining[4..8].try_into().unwrap();
+        let (header_size, size) = match u32::from_be_bytes(remaining[..4].try_into().unwrap()) {
+            0 => (8, remaining.len()),
+            1 if remaining.len() >= 16 => {
+                let size = u64::from_be_bytes(remaining[8..16].try_into().unwrap());
+                (16, usize::try_from(size).unwrap_or(usize::MAX))
+            }
+            1 => return Err(invalid("truncated box header")),
+            size => (8, size as usize),
+        };
+        if size < header_size
+            || offset
+                .checked_add(size)
+                .is_none_or(|end| end > payload.len())
+        {
+            return Err(invalid("invalid box size"));
+        }
+        children.push((box_type, offset + header_size..offset + size));
+        offset += size;
+    }
+    Ok(children)
+}
+
+/// Probe an MP4 file.
+pub fn probe<R: Read + Seek>(reader: &mut R) -> Result<Probe> {
+    let boxes = top_level_boxes(reader)?;
+    let moov = boxes
+        .iter()
+        .find(|b| &b.box_type == b"moov")
+        .ok_or_else(|| invalid("missing moov box"))?;
+    let moov = read_moov(reader, moov)?;
+
+    let mut duration_seconds = None;
+    let mut kind = Kind::Audio;
+    for (box_type, payload) in children(&moov)? {
+        match &box_type {
+            b"mvhd" => duration_seconds = header_duration(payload),
+            b"trak" if track_handler(payload)? == Some(*b"vide") => kind = Kind::Video,
+            _ => {}
+        }
+    }
+
+    let mime_type = match kind {
+        Kind::Audio => "audio/mp4",
+        Kind::Video => "video/mp4",
+    };
+
+    Ok(Probe {
+        duration_seconds,
+        kind,
+        mime_type,
+        faststart: is_faststart(&boxes),
+    })
+}
+
+/// Return the duration in a `mvhd` or `mdhd` payload, unless it is unknown.
+fn header_duration(payload: &[u8]) -> Option<f64> {
+    let (timescale, duration) = match payload.first()? {
+        0 => (
+            u32::from_be_bytes(payload.get(12..16)?.try_into().ok()?),
+            u32::from_be_bytes(payload.get(16..20)?.try_into().ok()?) as u64,
+        ),
+        1 => (
+            u32::from_be_bytes(payload.get(20..24)?.try_into().ok()?),
+            u64::from_be_bytes(payload.get(24..32)?.try_into().ok()?),
+        ),
+        _ => return None,
+    };
+    let unknown = duration == 0 || duration == u32::MAX as u64 || duration == u64::MAX;
+    if timescale == 0 || unknown {
+        return None;
+    }
+    Some(duration as f64 / timescale as f64)
+}
+
+/// Return the handler type (`soun`, `vide`, …) of a `trak` payload.
+fn track_handler(trak: &[u8]) -> Result<Option<[u8; 4]>> {
+    for (box_type, payload) in children(trak)? {
+        if &box_type != b"mdia" {
+            continue;
+        }
+        for (box_type, payload) in children(payload)? {
+            if &box_type == b"hdlr" && payload.len() >= 12 {
+                return Ok(Some(payload[8..12].try_into().unwrap()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Return a box with the given type and payload.
+    pub(crate) fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut content = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        content.extend_from_slice(box_type);
+        content.extend_from_slice(payload);
+        content
+    }
+
+    /// Return a version 0 `mvhd` box.
+    pub(crate) fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut payload = vec![0_u8; 12];
+        payload.extend_from_slice(&timescale.to_be_bytes());
+        payload.extend_from_slice(&duration.to_be_bytes());
+        payload.resize(100, 0);
+        mp4_box(b"mvhd", &payload)
+    }
+
+    /// Return a `trak` box with the given handler type.
+    pub(crate) fn trak(handler: &[u8; 4]) -> Vec<u8> {
+        let mut hdlr = vec![0_u8; 8];
+        hdlr.extend_from_slice(handler);
+        hdlr.resize(25, 0);
+        mp4_box(b"trak", &mp4_box(b"mdia", &mp4_box(b"hdlr", &hdlr)))
+    }
+
+    fn file(moov_children: &[Vec<u8>]) -> Vec<u8> {
+        let mut content = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        content.extend(mp4_box(b"moov", &moov_children.concat()));
+        content.extend(mp4_box(b"mdat", &[0_u8; 32]));
+        content
+    }
+
+    #[test]
+    fn probe_reads_duration_and_video_track() {
+        let content = file(&[mvhd(1000, 212_500), trak(b"soun"), trak(b"vide")]);
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        assert_eq!(probe.duration_seconds, Some(212.5));
+        assert_eq!(probe.kind, Kind::Video);
+        assert_eq!(probe.mime_type, "video/mp4");
+        assert_eq!(probe.faststart, Some(true));
+    }
+
+    #[test]
+    fn probe_detects_moov_after_mdat() {
+        let mut content = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        content.extend(mp4_box(b"mdat", &[0_u8; 32]));
+        content.extend(mp4_box(
+            b"moov",
+            &[mvhd(1000, 1000), trak(b"soun")].concat(),
+        ));
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        assert_eq!(probe.faststart, Some(false));
+    }
+
+    #[test]
+    fn probe_detects_audio_only_files() {
+        let content = file(&[mvhd(44_100, 0), trak(b"soun")]);
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        assert_eq!(probe.duration_seconds, None);
+        assert_eq!(probe.kind, Kind::Audio);
+        assert_eq!(probe.mime_type, "audio/mp4");
+    }
+
+    #[test]
+    fn top_level_boxes_lists_offsets_and_sizes() {
+        let content = file(&[mvhd(1000, 1000)]);
+
+        let boxes = top_level_boxes(&mut Cursor::new(&content)).unwrap();
+
+        let types: Vec<_> = boxes.iter().map(|b| &b.box_type).collect();
+        assert_eq!(types, [b"ftyp", b"moov", b"mdat"]);
+        assert_eq!(boxes[1].offset, boxes[0].size);
+        assert_eq!(boxes[2].offset + boxes[2].size, content.len() as u64);
+    }
+
+    #[test]
+    fn probe_rejects_missing_moov_and_truncated_boxes() {
+        let no_moov = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        assert!(probe(&mut Cursor::new(no_moov)).is_err());
+
+        let mut truncated = file(&[mvhd(1000, 1000)]);
+        truncated.truncate(truncated.len() - 1);
+        assert!(probe(&mut Cursor::new(truncated)).is_err());
+    }
+
+    #[test]
+    fn oversized_largesize_is_rejected_without_overflow() {
+        let mut content = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        let mut free = vec![0, 0, 0, 1];
+        free.extend_from_slice(b"free");
+        free.extend_from_slice(&(u64::MAX - 4).to_be_bytes());
+        content.extend_from_slice(&free);
+        assert!(top_level_boxes(&mut Cursor::new(&content)).is_err());
+
+        let mut payload = mp4_box(b"mvhd", &[0; 4]);
+        payload.extend_from_slice(&free);
+        assert!(child_ranges(&payload).is_err());
+
+        // Corrupting any bytes of a valid file gives an error or a result, never a panic.
+        let valid = file(&[mvhd(1000, 1000)]);
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..1000 {
+            let mut corrupted = valid.clone();
+            for _ in 0..4 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let index = (state % corrupted.len() as u64) as usize;
+                corrupted[index] = (state >> 32) as u8;
+            }
+            let _ = probe(&mut Cursor::new(corrupted));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Ogg files (Opus, Vorbis and Theora streams).
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{Kind, Probe, invalid, read_up_to};
+use crate::Result;
+
+/// Number of bytes searched for the last page at the end of the file.
+const TAIL_SIZE: u64 = 64 * 1024;
+
+/// Size of an Ogg page header, segment table excluded.
+const PAGE_HEADER_SIZE: usize = 27;
+
+/// Codec of a logical stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    /// Opus, with its pre-skip in samples at 48 kHz.
+    Opus { pre_skip: u16 },
+    /// Vorbis, with its sample rate.
+    Vorbis { sample_rate: u32 },
+    /// Theora video.
+    Theora,
+    /// Any other codec.
+    Other,
+}
+
+/// A page of an Ogg file.
+struct Page<'a> {
+    /// Beginning of stream.
+    bos: bool,
+    granule_position: i64,
+    serial: u32,
+    payload: &'a [u8],
+    /// Size of the page, header included.
+    size: usize,
+}
+
+impl<'a> Page<'a> {
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if !bytes.starts_with(b"OggS") || bytes.len() < PAGE_HEADER_SIZE {
+            return None;
+        }
+        let segments = bytes[26] as usize;
+        let table = bytes.get(PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + segments)?;
+        let header_size = PAGE_HEADER_SIZE + segments;
+        let payload_size: usize = table.iter().map(|s| *s as usize).sum();
+        Some(Self {
+            bos: bytes[5] & 0x02 != 0,
+            granule_position: i64::from_le_bytes(bytes[6..14].try_into().unwrap()),
+            serial: u32::from_le_bytes(bytes[14..18].try_into().unwrap()),
+            payload: bytes.get(header_size..header_size + payload_size)?,
+            size: header_size + payload_size,
+        })
+    }
+}
+
+fn codec(packet: &[u8]) -> Codec {
+    if packet.starts_with(b"OpusHead") && packet.len() >= 12 {
+        Codec::Opus {
+            pre_skip: u16::from_le_bytes(packet[10..12].try_into().unwrap()),
+        }
+    } else if packet.starts_with(b"\x01vorbis") && packet.len() >= 16 {
+        Codec::Vorbis {
+            sample_rate: u32::from_le_bytes(packet[12..16].try_into().unwrap()),
+        }
+    } else if packet.starts_with(b"\x80theora") {
+        Codec::Theora
+    } else {
+        Codec::Other
+    }
+}
+
+/// Probe an Ogg file.
+pub fn probe<R: Read + Seek>(reader: &mut R) -> Result<Probe> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+
+    // The beginning-of-stream pages of all logical streams come first.
+    reader.seek(SeekFrom::Start(0))?;
+    let mut head = vec![0_u8; TAIL_SIZE as usize];
+    let read = read_up_to(reader, &mut head)?;
+    head.truncate(read);
+
+    let mut streams = Vec::new();
+    let mut offset = 0;
+    while let Some(page) = Page::parse(&head[offset..]) {
+        if !page.bos {
+            break;
+        }
+        streams.push((page.serial, codec(page.payload)));
+        offset += page.size;
+    }
+    if streams.is_empty() {
+        return Err(invalid("no Ogg stream found"));
+    }
+
+    let kind = if streams.iter().any(|(_, codec)| *codec == Codec::Theora) {
+        Kind::Video
+    } else {
+        Kind::Audio
+    };
+
+    // The duration comes from the granule position of the last page of the audio stream.
+    let audio = streams
+        .iter()
+        .find(|(_, codec)| matches!(codec, Codec::Opus { .. } | Codec::Vorbis { .. }));
+    let duration_seconds = match audio {
+        Some((serial, codec)) => {
+            let tail_offset = file_size.saturating_sub(TAIL_SIZE);
+            reader.seek(SeekFrom::Start(tail_offset))?;
+            let mut tail = Vec::new();
+            reader.read_to_end(&mut tail)?;
+
+            let granule_position = (0..tail.len())
+                .rev()
+                .filter_map(|i| Page::parse(&tail[i..]))
+                .find(|page| page.serial == *serial && page.granule_position >= 0)
+                .map(|page| page.granule_position);
+
+            granule_position.and_then(|granule_position| match codec {
+                Codec::Opus { pre_skip } => {
+                    Some((granule_position - *pre_skip as i64).max(0) as f64 / 48_000.0)
+                }
+                Codec::Vorbis { sample_rate } if *sample_rate > 0 => {
+                    Some(granule_position as f64 / *sample_rate as f64)
+                }
+                _ => None,
+            })
+        }
+        None => None,
+    };
+
+    let mime_type = match kind {
+        Kind::Audio => "audio/ogg",
+        Kind::Video => "video/ogg",
+    };
+
+    Ok(Probe {
+        duration_seconds,
+        kind,
+        mime_type,
+        faststart: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn page(bos: bool, granule_position: i64, serial: u32, payload: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0".to_vec();
+        page.push(if bos { 0x02 } else { 0x00 });
+        page.extend(granule_position.to_le_bytes());
+        page.extend(serial.to_le_bytes());
+        page.extend([0_u8; 8]);
+        page.push(1);
+        page.push(payload.len() as u8);
+        page.extend(payload);
+        page
+    }
+
+    #[test]
+    fn probe_reads_opus_duration() {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend(312_u16.to_le_bytes());
+        head.extend([0_u8; 7]);
+        let mut content = page(true, 0, 7, &head);
+        content.extend(page(false, 0, 7, b"OpusTags"));
+        content.extend(page(false, 48_000 * 90 + 312, 7, &[0_u8; 100]));
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        assert_eq!(probe.duration_seconds, Some(90.0));
+        assert_eq!(probe.kind, Kind::Audio);
+        assert_eq!(probe.mime_type, "audio/ogg");
+    }
+
+    #[test]
+    fn probe_reads_vorbis_duration_next_to_theora_video() {
+        let mut vorbis = b"\x01vorbis".to_vec();
+        vorbis.extend([0_u8; 5]);
+        vorbis.extend(44_100_u32.to_le_bytes());
+        vorbis.extend([0_u8; 14]);
+        let mut content = page(true, 0, 1, b"\x80theora");
+        content.extend(page(true, 0, 2, &vorbis));
+        content.extend(page(false, 44_100 * 3, 2, &[0_u8; 10]));
+        content.extend(page(false, 99_999, 1, &[0_u8; 10]));
+
+        let probe = probe(&mut Cursor::new(content)).unwrap();
+
+        assert_eq!(probe.duration_seconds, Some(3.0));
+        assert_eq!(probe.kind, Kind::Video);
+        assert_eq!(probe.mime_type, "video/ogg");
+    }
+
+    #[test]
+    fn probe_rejects_files_without_streams() {
+        assert!(probe(&mut Cursor::new(b"OggS".to_vec())).is_err());
+    }
+}
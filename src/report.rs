@@ -0,0 +1,335 @@
+//! Machine-readable summary of a generation run.
+//!
+//! A [`Report`] is built from the [`Event`]s of a [`FeedGenerator`](crate::generator::FeedGenerator)
+//! and saved as JSON, for monitoring:
+//!
+//! ```json
+//! {
+//!   "started_at": "2026-10-19T08:00:00Z",
+//!   "wall_time_seconds": 12.5,
+//!   "error": null,
+//!   "phases": { "discovery_seconds": 0.1, "parsing_seconds": 2.0, ... },
+//!   "channels": [{ "channel": "Cooking", "episodes": 42, "added": ["dQw4w9WgXcQ"], ... }]
+//! }
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::{
+    Result,
+    convert::{Stats, Timings},
+    error::Error,
+    generator::Event,
+};
+
+/// Summary of a generation run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Start of the run, in RFC 3339 format.
+    pub started_at: String,
+    /// Duration of the run.
+    pub wall_time_seconds: f64,
+    /// Error that stopped the run, if any.
+    pub error: Option<String>,
+    /// Time spent in each phase, for all channels.
+    pub phases: Phases,
+    /// Channels processed, in order.
+    pub channels: Vec<ChannelReport>,
+    #[serde(skip)]
+    start: Instant,
+}
+
+/// Summary of the generation of the feeds of a channel directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelReport {
+    /// Name of the channel directory.
+    pub channel: String,
+    /// Path of the channel directory.
+    pub dir: PathBuf,
+    /// Paths of the feeds written.
+    pub feeds: Vec<PathBuf>,
+    /// Number of episodes in the feeds.
+    pub episodes: usize,
+    /// GUIDs of the episodes absent from the previously written feeds.
+    pub added: Vec<String>,
+    /// GUIDs of the episodes no longer in the feeds.
+    pub removed: Vec<String>,
+    /// Total size of the media files, in bytes.
+    pub media_bytes: u64,
+    /// Total duration of the episodes, in seconds.
+    pub duration_seconds: u64,
+    /// Number of artwork files derived from their source image.
+    pub artwork_generated: usize,
+    /// Number of artwork files already up to date.
+    pub artwork_reused: usize,
+    /// Problems that did not prevent the generation of the feeds.
+    pub warnings: Vec<String>,
+    /// Error that stopped the generation of the feeds, if any.
+    pub error: Option<String>,
+    /// Time spent on the channel.
+    pub wall_time_seconds: f64,
+    /// Time spent in each phase.
+    pub phases: Phases,
+    #[serde(skip)]
+    start: Instant,
+    #[serde(skip)]
+    finished: bool,
+    #[serde(skip)]
+    splits: Vec<Option<String>>,
+}
+
+/// Time spent in each phase of a generation, in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Phases {
+    /// Listing the info files.
+    pub discovery_seconds: f64,
+    /// Parsing the info files and probing the media files.
+    pub parsing_seconds: f64,
+    /// Deriving the artwork.
+    pub images_seconds: f64,
+    /// Rendering the feeds to xml and writing them.
+    pub rendering_seconds: f64,
+}
+
+impl Phases {
+    fn add(&mut self, timings: &Timings) {
+        self.discovery_seconds += timings.discovery.as_secs_f64();
+        self.parsing_seconds += timings.parsing.as_secs_f64();
+        self.images_seconds += timings.images.as_secs_f64();
+        self.rendering_seconds += timings.rendering.as_secs_f64();
+    }
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Report {
+    /// Start a report for a run starting now.
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            wall_time_seconds: 0.0,
+            error: None,
+            phases: Phases::default(),
+            channels: Vec::new(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Record the progress reported by `event`.
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::ChannelStarted { channel, dirpath } => {
+                self.channels.push(ChannelReport::new(channel, dirpath));
+            }
+            Event::Warning { channel, message } => {
+                // Subscriber feeds repeat the warnings of the channel.
+                if let Some(report) = self.channel(channel)
+                    && !report.warnings.contains(message)
+                {
+                    report.warnings.push(message.clone());
+                }
+            }
+            Event::FeedGenerated {
+                channel,
+                split,
+                path,
+                stats,
+                added,
+                removed,
+                ..
+            } => {
+                self.phases.add(&stats.timings);
+                if let Some(report) = self.channel(channel) {
+                    report.add(split.as_deref(), path.as_deref(), stats, added, removed);
+                }
+            }
+            Event::ChannelFinished { channel } => {
+                if let Some(report) = self.channel(channel) {
+                    report.finished = true;
+                    report.wall_time_seconds = report.start.elapsed().as_secs_f64();
+                }
+            }
+        }
+    }
+
+    /// Record the `error` that stopped the run, against the channel being processed if any.
+    pub fn fail(&mut self, error: &Error) {
+        self.error = Some(error.to_string());
+        if let Some(report) = self.channels.last_mut().filter(|report| !report.finished) {
+            report.error = Some(error.to_string());
+            report.wall_time_seconds = report.start.elapsed().as_secs_f64();
+        }
+    }
+
+    /// Record the end of the run.
+    pub fn finish(&mut self) {
+        self.wall_time_seconds = self.start.elapsed().as_secs_f64();
+    }
+
+    /// Save the report as JSON to `filepath`.
+    pub fn save<P: AsRef<Path>>(&self, filepath: P) -> Result<()> {
+        std::fs::write(filepath, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn channel(&mut self, channel: &str) -> Option<&mut ChannelReport> {
+        self.channels
+            .iter_mut()
+            .rev()
+            .find(|report| report.channel == channel)
+    }
+}
+
+impl ChannelReport {
+    fn new(channel: &str, dirpath: &Path) -> Self {
+        Self {
+            channel: channel.to_string(),
+            dir: dirpath.to_path_buf(),
+            feeds: Vec::new(),
+            episodes: 0,
+            added: Vec::new(),
+            removed: Vec::new(),
+            media_bytes: 0,
+            duration_seconds: 0,
+            artwork_generated: 0,
+            artwork_reused: 0,
+            warnings: Vec::new(),
+            error: None,
+            wall_time_seconds: 0.0,
+            phases: Phases::default(),
+            start: Instant::now(),
+            finished: false,
+            splits: Vec::new(),
+        }
+    }
+
+    /// Add a feed of the channel. Subscriber feeds list the same episodes, so the figures
+    /// about episodes are only summed over the main and split feeds.
+    fn add(
+        &mut self,
+        split: Option<&str>,
+        path: Option<&Path>,
+        stats: &Stats,
+        added: &[String],
+        removed: &[String],
+    ) {
+        self.feeds.extend(path.map(Path::to_path_buf));
+        let split = split.map(str::to_string);
+        if !self.splits.contains(&split) {
+            self.splits.push(split);
+            self.episodes += stats.episodes;
+            self.media_bytes += stats.media_bytes;
+            self.duration_seconds += stats.duration_seconds;
+        }
+        for guid in added {
+            if !self.added.contains(guid) {
+                self.added.push(guid.clone());
+            }
+        }
+        for guid in removed {
+            if !self.removed.contains(guid) {
+                self.removed.push(guid.clone());
+            }
+        }
+        self.artwork_generated += stats.artwork_generated;
+        self.artwork_reused += stats.artwork_reused;
+        self.phases.add(&stats.timings);
+        self.wall_time_seconds = self.start.elapsed().as_secs_f64();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn feed_generated(subscriber: &str, split: Option<&str>, added: &[&str]) -> Event {
+        Event::FeedGenerated {
+            channel: "Cooking".into(),
+            dirpath: "/data/Cooking".into(),
+            subscriber: Some(subscriber.into()),
+            split: split.map(str::to_string),
+            path: Some(format!("/data/Cooking.{subscriber}.xml").into()),
+            stats: Box::new(Stats {
+                episodes: 2,
+                media_bytes: 100,
+                artwork_generated: 1,
+                timings: Timings {
+                    parsing: Duration::from_millis(500),
+                    ..Timings::default()
+                },
+                ..Stats::default()
+            }),
+            added: added.iter().map(|guid| guid.to_string()).collect(),
+            removed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn report_aggregates_feeds_of_a_channel() {
+        let mut report = Report::new();
+        report.record(&Event::ChannelStarted {
+            channel: "Cooking".into(),
+            dirpath: "/data/Cooking".into(),
+        });
+        report.record(&Event::Warning {
+            channel: "Cooking".into(),
+            message: "not faststart".into(),
+        });
+        report.record(&feed_generated("alice", None, &["a", "b"]));
+        report.record(&feed_generated("bob", None, &["a"]));
+        report.record(&Event::ChannelFinished {
+            channel: "Cooking".into(),
+        });
+        report.finish();
+
+        let channel = &report.channels[0];
+        assert_eq!(channel.feeds.len(), 2);
+        assert_eq!(channel.episodes, 2);
+        assert_eq!(channel.media_bytes, 100);
+        assert_eq!(channel.added, vec!["a", "b"]);
+        assert_eq!(channel.artwork_generated, 2);
+        assert_eq!(channel.warnings, vec!["not faststart"]);
+        assert_eq!(channel.phases.parsing_seconds, 1.0);
+        assert_eq!(report.phases.parsing_seconds, 1.0);
+        assert_eq!(report.error, None);
+
+        // Split feeds list other episodes.
+        report.record(&feed_generated("alice", Some("Clips"), &["c"]));
+        let channel = &report.channels[0];
+        assert_eq!(channel.feeds.len(), 3);
+        assert_eq!(channel.episodes, 4);
+        assert_eq!(channel.added, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn errors_are_recorded_against_the_unfinished_channel() {
+        let mut report = Report::new();
+        report.record(&Event::ChannelStarted {
+            channel: "Cooking".into(),
+            dirpath: "/data/Cooking".into(),
+        });
+        report.fail(&Error::MissingChannelInfoFile("/data/Cooking".into()));
+
+        let directory = tempfile::tempdir().unwrap();
+        let filepath = directory.path().join("report.json");
+        report.save(&filepath).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(filepath).unwrap()).unwrap();
+        assert!(json["error"].as_str().unwrap().contains("missing channel"));
+        assert_eq!(json["channels"][0]["error"], json["error"]);
+        assert_eq!(json["channels"][0]["episodes"], 0);
+    }
+}
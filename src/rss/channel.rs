@@ -1,11 +1,11 @@
 //! RSS Channel.
 
-use hard_xml::XmlWrite;
+use hard_xml::{XmlRead, XmlWrite};
 
 use super::episode::Episode;
 
 /// Represents the `channel` element.
-#[derive(Debug, PartialEq, Eq, XmlWrite)]
+#[derive(Debug, PartialEq, Eq, XmlRead, XmlWrite)]
 #[xml(tag = "channel")]
 pub struct Channel {
     /// Title of the channel.
@@ -21,7 +21,7 @@ pub struct Channel {
     pub link: String,
 
     /// Image of the channel.
-    #[xml(child = "image")]
+    #[xml(child = "itunes:image")]
     pub image: Image,
 
     /// Author of the channel.
@@ -40,9 +40,9 @@ pub struct Channel {
     #[xml(flatten_text = "pubDate")]
     pub pub_date: String,
 
-    /// Category of the channel.
-    #[xml(flatten_text = "category")]
-    pub category: String,
+    /// Apple Podcasts categories of the channel.
+    #[xml(child = "itunes:category")]
+    pub categories: Vec<Category>,
 
     /// Generator of the channel.
     #[xml(flatten_text = "generator")]
@@ -61,8 +61,21 @@ pub struct Channel {
     pub episodes: Vec<Episode>,
 }
 
+/// Apple Podcasts category of the channel, with its subcategories.
+#[derive(Debug, Clone, PartialEq, Eq, XmlRead, XmlWrite)]
+#[xml(tag = "itunes:category")]
+pub struct Category {
+    /// Name of the category.
+    #[xml(attr = "text")]
+    pub text: String,
+
+    /// Subcategories of the category.
+    #[xml(child = "itunes:category")]
+    pub subcategories: Vec<Category>,
+}
+
 /// Image for the channel.
-#[derive(Debug, PartialEq, Eq, XmlWrite)]
+#[derive(Debug, PartialEq, Eq, XmlRead, XmlWrite)]
 #[xml(tag = "itunes:image")]
 pub struct Image {
     /// URL of the channel's image file.
@@ -87,7 +100,13 @@ mod tests {
             language: "en".into(),
             last_build_date: "Mon, 01 Jan 2024 00:00:00 +0000".into(),
             pub_date: "Mon, 01 Jan 2024 00:00:00 +0000".into(),
-            category: "Technology & Science".into(),
+            categories: vec![Category {
+                text: "Society & Culture".into(),
+                subcategories: vec![Category {
+                    text: "Documentary".into(),
+                    subcategories: Vec::new(),
+                }],
+            }],
             generator: "ytdlp".into(),
             explicit_content: "false".into(),
             channel_type: "Serial".into(),
@@ -101,5 +120,9 @@ mod tests {
         assert!(xml.contains("<![CDATA[Description with <markup>]]>"));
         assert!(xml.contains(r#"href="https://example.com/image.jpg""#));
         assert!(xml.contains("<itunes:type>Serial</itunes:type>"));
+        assert!(xml.contains(
+            r#"<itunes:category text="Society &amp; Culture"><itunes:category text="Documentary"/></itunes:category>"#
+        ));
+        assert!(!xml.contains("<category>"));
     }
 }
@@ -1,14 +1,14 @@
 //! RSS Episode.
 
-use hard_xml::XmlWrite;
+use hard_xml::{XmlRead, XmlWrite};
 
 /// Represents a podcasts episode.
-#[derive(Debug, PartialEq, Eq, XmlWrite)]
+#[derive(Debug, PartialEq, Eq, XmlRead, XmlWrite)]
 #[xml(tag = "item")]
 pub struct Episode {
     /// GUID of the episode.
-    #[xml(flatten_text = "guid")]
-    pub guid: String,
+    #[xml(child = "guid")]
+    pub guid: Guid,
 
     /// Publication date of the episode.
     #[xml(flatten_text = "pubDate")]
@@ -38,17 +38,46 @@ pub struct Episode {
     #[xml(child = "itunes:image")]
     pub image: Image,
 
-    /// Duration of the episode.
+    /// Duration of the episode, in seconds.
     #[xml(flatten_text = "itunes:duration")]
-    pub duration: String,
+    pub duration: Option<String>,
 
     /// Classification of the episode.
     #[xml(flatten_text = "itunes:explicit")]
     pub explicit_content: String,
+
+    /// Title of the episode for podcast apps.
+    #[xml(flatten_text = "itunes:title", cdata)]
+    pub itunes_title: Option<String>,
+
+    /// Short description of the episode.
+    #[xml(flatten_text = "itunes:subtitle", cdata)]
+    pub subtitle: Option<String>,
+
+    /// Description of the episode for podcast apps.
+    #[xml(flatten_text = "itunes:summary", cdata)]
+    pub summary: Option<String>,
+
+    /// Comma-separated keywords of the episode.
+    #[xml(flatten_text = "itunes:keywords")]
+    pub keywords: Option<String>,
+}
+
+/// Globally unique identifier of an episode.
+#[derive(Debug, Clone, PartialEq, Eq, XmlRead, XmlWrite)]
+#[xml(tag = "guid")]
+pub struct Guid {
+    /// Whether the GUID is the URL of the episode. Clients assume it is when unset.
+    #[xml(attr = "isPermaLink")]
+    pub is_perma_link: Option<bool>,
+
+    /// Value of the GUID.
+    #[xml(text)]
+    pub value: String,
 }
 
 /// Player metadata for an episode.
-#[derive(Debug, PartialEq, Eq, XmlWrite)]
+#[derive(Debug, PartialEq, Eq, XmlRead, XmlWrite)]
 #[xml(tag = "enclosure")]
 pub struct Enclosure {
     /// URL of the episode's video file.
@@ -65,7 +94,7 @@ pub struct Enclosure {
 }
 
 /// Image for an episode.
-#[derive(Debug, PartialEq, Eq, XmlWrite)]
+#[derive(Debug, PartialEq, Eq, XmlRead, XmlWrite)]
 #[xml(tag = "itunes:image")]
 pub struct Image {
     /// URL of the episode's image file.
@@ -79,7 +108,10 @@ mod tests {
 
     fn sample_episode() -> Episode {
         Episode {
-            guid: "abc123".into(),
+            guid: Guid {
+                is_perma_link: Some(false),
+                value: "abc123".into(),
+            },
             pub_date: "Sun, 01 Jan 2023 09:10:11 +0000".into(),
             title: "Test Episode".into(),
             link: "https://youtube.com/watch?v=abc123".into(),
@@ -93,8 +125,12 @@ mod tests {
             image: Image {
                 file_url: "https://cdn.example.com/thumb.png".into(),
             },
-            duration: "600".into(),
+            duration: Some("600".into()),
             explicit_content: "false".into(),
+            itunes_title: None,
+            subtitle: None,
+            summary: None,
+            keywords: None,
         }
     }
 
@@ -102,7 +138,7 @@ mod tests {
     fn episode_serializes_to_xml() {
         let xml = sample_episode().to_string().unwrap();
         assert!(xml.contains("<item>"));
-        assert!(xml.contains("<guid>abc123</guid>"));
+        assert!(xml.contains(r#"<guid isPermaLink="false">abc123</guid>"#));
         assert!(xml.contains("<![CDATA[Test Episode]]>"));
         assert!(xml.contains(r#"url="https://cdn.example.com/ep.mp4""#));
         assert!(xml.contains(r#"length="99999""#));
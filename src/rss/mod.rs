@@ -1,14 +1,26 @@
 //! Struct and functions for RSS feed.
 
-use hard_xml::XmlWrite;
+use hard_xml::{XmlRead, XmlWrite};
 
 pub mod channel;
 pub mod episode;
 
 use channel::Channel;
 
+/// Version of the RSS feeds.
+pub const VERSION: &str = "2.0";
+
+/// Namespace of the iTunes podcast extensions.
+pub const ITUNES_NAMESPACE: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+
+/// Namespace of the RSS content module.
+pub const CONTENT_NAMESPACE: &str = "http://purl.org/rss/1.0/modules/content/";
+
+/// XML declaration preceding the rendered feeds.
+pub(crate) const XML_PROLOG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
 /// Represents a RSS feed for a podcast.
-#[derive(Debug, PartialEq, Eq, XmlWrite)]
+#[derive(Debug, PartialEq, Eq, XmlRead, XmlWrite)]
 #[xml(tag = "rss")]
 pub struct Rss {
     /// Identifier.
@@ -28,3 +40,75 @@ pub struct Rss {
     pub channel: Channel,
     // pub episodes: Vec<Episode>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rss::{
+        channel::Image,
+        episode::{self, Episode},
+    };
+
+    #[test]
+    fn rendered_feed_reads_back() {
+        let episode = Episode {
+            guid: episode::Guid {
+                is_perma_link: Some(false),
+                value: "https://example.com/?a&b".into(),
+            },
+            pub_date: "Sun, 01 Jan 2023 09:10:11 +0000".into(),
+            title: "Q&A <live>".into(),
+            link: "https://youtube.com/watch?v=abc123".into(),
+            description: "A test".into(),
+            enclosure: episode::Enclosure {
+                file_url: "https://cdn.example.com/ep.mp4?token=a&b".into(),
+                file_length: "99999".into(),
+                file_type: "video/mp4".into(),
+            },
+            author: "Author".into(),
+            image: episode::Image {
+                file_url: "https://cdn.example.com/thumb.png".into(),
+            },
+            duration: None,
+            explicit_content: "false".into(),
+            itunes_title: Some("Q&A <live>".into()),
+            subtitle: Some("A test".into()),
+            summary: Some("A test".into()),
+            keywords: Some("q&a,live".into()),
+        };
+        let feed = Rss {
+            version: "2.0".into(),
+            namespace: "http://www.itunes.com/dtds/podcast-1.0.dtd".into(),
+            content_namespace: "http://purl.org/rss/1.0/modules/content/".into(),
+            channel: Channel {
+                title: "A & B".into(),
+                description: "Description".into(),
+                link: "https://example.com/feed".into(),
+                image: Image {
+                    image_url: "https://example.com/image.jpg".into(),
+                },
+                author: "Author".into(),
+                language: "en".into(),
+                last_build_date: "Mon, 01 Jan 2024 00:00:00 +0000".into(),
+                pub_date: "Mon, 01 Jan 2024 00:00:00 +0000".into(),
+                categories: vec![channel::Category {
+                    text: "Education".into(),
+                    subcategories: vec![channel::Category {
+                        text: "How To".into(),
+                        subcategories: Vec::new(),
+                    }],
+                }],
+                generator: "ytdlp".into(),
+                explicit_content: "false".into(),
+                channel_type: "Serial".into(),
+                episodes: vec![episode],
+            },
+        };
+
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+            feed.to_string().unwrap()
+        );
+        assert_eq!(Rss::from_str(&xml).unwrap(), feed);
+    }
+}
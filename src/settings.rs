@@ -0,0 +1,282 @@
+//! Settings from the optional TOML configuration file.
+//!
+//! Top-level keys set the defaults for every channel directory, and each
+//! `[channels."<directory name>"]` table overrides some of them for one directory:
+//!
+//! ```toml
+//! [artwork.channel]
+//! size = 3000
+//!
+//! [channels."Cooking".artwork.episode]
+//! fill = "blur"
+//! ```
+//!
+//! Run hooks, in `[[hooks.run]]`, and virtual feeds, in `[virtual."<name>"]`, are the only
+//! settings that are not per channel.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    Result, access, aggregate, artwork, category, error::Error, filter, guid, history, hooks, info,
+    itunes, source, split,
+};
+
+/// Settings that apply to a single channel directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelSettings {
+    /// Name of the source of the channel metadata, `yt-dlp` by default, see
+    /// [`source`](crate::source).
+    pub source: Option<String>,
+    /// Channel metadata replacing the metadata parsed from the source.
+    pub metadata: source::Overrides,
+    /// Resolution of several channel info files.
+    pub playlist: info::channel::Settings,
+    /// Artwork options.
+    pub artwork: artwork::Settings,
+    /// Access tokens for private feeds.
+    pub access: access::Settings,
+    /// Commands run after each feed is written.
+    pub hooks: hooks::Settings,
+    /// Episode fields kept from the previous version of the feed.
+    pub history: history::Settings,
+    /// Format of the episode GUIDs.
+    pub guid: guid::Settings,
+    /// iTunes tags mapped from the episode metadata.
+    pub itunes: itunes::Settings,
+    /// Apple Podcasts categories of the channel.
+    pub categories: category::Categories,
+    /// Rules selecting the episodes of the channel.
+    pub filter: filter::Settings,
+    /// Feeds split off the channel.
+    pub split: Vec<split::Split>,
+}
+
+/// Settings loaded from the configuration file.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Settings for channel directories without their own table.
+    defaults: ChannelSettings,
+    /// Settings for each channel directory with its own table, keyed by directory name.
+    channels: BTreeMap<String, ChannelSettings>,
+    /// Commands run once after all feeds are written.
+    run_hooks: Vec<hooks::Hook>,
+    /// Virtual feeds, keyed by name.
+    virtual_feeds: BTreeMap<String, aggregate::Feed>,
+}
+
+impl Settings {
+    /// Load the settings from the TOML file at `filepath`.
+    pub fn load<P: AsRef<Path>>(filepath: P) -> Result<Self> {
+        let content = std::fs::read_to_string(filepath.as_ref())?;
+        Self::from_toml(&content).map_err(|e| match e {
+            Error::ConfigError(msg) => {
+                Error::ConfigError(format!("{}: {msg}", filepath.as_ref().display()))
+            }
+            e => e,
+        })
+    }
+
+    /// Parse the settings from the content of a TOML file.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(content).map_err(config_error)?;
+
+        let overrides = match table.remove("channels") {
+            None => toml::Table::new(),
+            Some(toml::Value::Table(overrides)) => overrides,
+            Some(_) => return Err(Error::ConfigError("`channels` must be a table".into())),
+        };
+
+        let run_hooks = match table.get_mut("hooks") {
+            Some(toml::Value::Table(hooks)) => match hooks.remove("run") {
+                Some(value) => value
+                    .try_into()
+                    .map_err(|e| Error::ConfigError(format!("hooks.run: {e}")))?,
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+
+        let virtual_feeds: BTreeMap<String, aggregate::Feed> = match table.remove("virtual") {
+            Some(value) => value
+                .try_into()
+                .map_err(|e| Error::ConfigError(format!("virtual: {e}")))?,
+            None => BTreeMap::new(),
+        };
+        if let Some(name) = virtual_feeds.keys().find(|name| !split::is_filename(name)) {
+            return Err(Error::ConfigError(format!(
+                "virtual: invalid feed name `{name}`: must be a filename without path separators"
+            )));
+        }
+
+        let defaults: ChannelSettings = table.clone().try_into().map_err(config_error)?;
+
+        let mut channels = BTreeMap::new();
+        for (name, value) in overrides {
+            let toml::Value::Table(channel_table) = value else {
+                return Err(Error::ConfigError(format!(
+                    "`channels.{name}` must be a table"
+                )));
+            };
+            let mut merged = table.clone();
+            merge(&mut merged, channel_table);
+            let settings = merged
+                .try_into()
+                .map_err(|e| Error::ConfigError(format!("channels.{name}: {e}")))?;
+            channels.insert(name, settings);
+        }
+
+        Ok(Self {
+            defaults,
+            channels,
+            run_hooks,
+            virtual_feeds,
+        })
+    }
+
+    /// Return the settings for the channel directory named `name`.
+    pub fn channel(&self, name: &str) -> &ChannelSettings {
+        self.channels.get(name).unwrap_or(&self.defaults)
+    }
+
+    /// Return the commands run once after all feeds are written.
+    pub fn run_hooks(&self) -> &[hooks::Hook] {
+        &self.run_hooks
+    }
+
+    /// Return the virtual feeds, keyed by name.
+    pub fn virtual_feeds(&self) -> &BTreeMap<String, aggregate::Feed> {
+        &self.virtual_feeds
+    }
+}
+
+/// Recursively merge `overlay` into `base`, with values in `overlay` taking precedence.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn config_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::ConfigError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_uses_defaults() {
+        let settings = Settings::from_toml("").unwrap();
+        assert_eq!(settings.channel("any"), &ChannelSettings::default());
+    }
+
+    #[test]
+    fn channel_table_overrides_defaults() {
+        let settings = Settings::from_toml(
+            r##"
+            [artwork.episode]
+            size = 3000
+            background = "#ffffff"
+
+            [channels."Cooking".artwork.episode]
+            fill = "blur"
+            "##,
+        )
+        .unwrap();
+
+        let defaults = settings.channel("Other");
+        assert_eq!(defaults.artwork.episode.size, 3000);
+        assert_eq!(defaults.artwork.episode.fill, artwork::Fill::Pad);
+        assert_eq!(defaults.artwork.channel, artwork::Options::default());
+
+        let cooking = settings.channel("Cooking");
+        assert_eq!(cooking.artwork.episode.size, 3000);
+        assert_eq!(
+            cooking.artwork.episode.background,
+            artwork::Color([255, 255, 255])
+        );
+        assert_eq!(cooking.artwork.episode.fill, artwork::Fill::Blur);
+    }
+
+    #[test]
+    fn run_hooks_are_global() {
+        let settings = Settings::from_toml(
+            r#"
+            [[hooks.run]]
+            command = ["notify"]
+
+            [[channels."Cooking".hooks.channel]]
+            command = ["rsync", "--archive"]
+            on_failure = "abort"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.run_hooks()[0].command, vec!["notify"]);
+        assert!(settings.channel("Other").hooks.channel.is_empty());
+        let cooking = &settings.channel("Cooking").hooks.channel[0];
+        assert_eq!(cooking.on_failure, hooks::OnFailure::Abort);
+        assert_eq!(cooking.timeout, 60);
+
+        let error = Settings::from_toml("[[channels.\"Cooking\".hooks.run]]\ncommand = [\"a\"]\n")
+            .unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.contains("run")));
+    }
+
+    #[test]
+    fn virtual_feeds_are_global() {
+        let settings = Settings::from_toml(
+            r#"
+            [virtual."All cooking"]
+            title = "All cooking"
+            artwork = "cooking.jpg"
+            channels = ["Cooking", "Baking"]
+            "#,
+        )
+        .unwrap();
+
+        let feed = &settings.virtual_feeds()["All cooking"];
+        assert_eq!(feed.channels, vec!["Cooking", "Baking"]);
+        assert_eq!(settings.channel("All cooking"), &ChannelSettings::default());
+
+        let error = Settings::from_toml("[virtual.\"All\"]\ntitle = \"All\"\n").unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.starts_with("virtual")));
+
+        for name in ["sub/All", "..", ""] {
+            let error = Settings::from_toml(&format!(
+                "[virtual.\"{name}\"]\ntitle = \"All\"\nartwork = \"all.jpg\"\nchannels = []\n"
+            ))
+            .unwrap_err();
+            assert!(
+                matches!(&error, Error::ConfigError(msg) if msg.contains("invalid feed name")),
+                "{name}: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let error = Settings::from_toml("[artwork.episode]\nsise = 3000\n").unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.contains("sise")));
+
+        let error =
+            Settings::from_toml("[channels.\"Cooking\".artwork]\nfill = \"blur\"\n").unwrap_err();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.starts_with("channels.Cooking")));
+
+        let error = Settings::from_toml("[channels.\"Cooking\"]\ncategories = [\"Arts/Fod\"]\n")
+            .unwrap_err();
+        assert!(
+            matches!(error, Error::ConfigError(msg) if msg.starts_with("channels.Cooking") && msg.contains("did you mean `Food`?"))
+        );
+    }
+}
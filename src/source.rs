@@ -0,0 +1,428 @@
+//! Sources of the channel and episode metadata.
+//!
+//! A [`MetadataSource`] discovers the channel and the episodes of a channel directory, and
+//! parses them into the [`Channel`] and [`Episode`] models converted to RSS. Two sources are
+//! built in: the `.info.json` files written by yt-dlp, the default, and the tags of local
+//! audio files. Other sources, e.g. a database export or the sidecar files of another
+//! downloader, are registered by name with
+//! [`FeedGeneratorBuilder::source`](crate::generator::FeedGeneratorBuilder::source), and
+//! selected per channel:
+//!
+//! ```toml
+//! [channels."Archive"]
+//! source = "database"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Local, NaiveDate, offset::Utc};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use crate::{Result, error::Error, info, local, settings::ChannelSettings};
+
+/// Name of the source of the channels without a `source` setting.
+pub const DEFAULT: &str = "yt-dlp";
+
+/// Discovers and parses the metadata of channel directories.
+///
+/// The methods return boxed futures so that sources can be registered as trait objects.
+pub trait MetadataSource: Send + Sync {
+    /// Return this source configured for a channel with `settings`, or `None` to use it as
+    /// is, the default.
+    ///
+    /// Sources with settings of their own read them here, such as the yt-dlp source, which
+    /// chooses among several playlist files with the `playlist` table.
+    fn configure(&self, settings: &ChannelSettings) -> Option<Arc<dyn MetadataSource>> {
+        let _ = settings;
+        None
+    }
+
+    /// Return the file describing the channel of the directory `dirpath`, or `None` if the
+    /// channel is described by its episodes only.
+    fn discover_channel<'a>(
+        &'a self,
+        dirpath: &'a Path,
+    ) -> BoxFuture<'a, Result<Option<ChannelFile>>>;
+
+    /// Return the episodes of the channel directory `dirpath`, before parsing.
+    fn discover_episodes<'a>(
+        &'a self,
+        dirpath: &'a Path,
+    ) -> BoxFuture<'a, Result<Vec<EpisodeFile>>>;
+
+    /// Parse `file` and return the episode along with its enclosure and image, or `None` if
+    /// the episode is not ready yet, e.g. while its media file is downloading.
+    fn parse_episode<'a>(
+        &'a self,
+        file: &'a EpisodeFile,
+    ) -> BoxFuture<'a, Result<Option<(Episode, Enclosure, PathBuf)>>>;
+
+    /// Parse the channel of the directory `dirpath` from the `file` found by
+    /// [`discover_channel`](Self::discover_channel), if any, and from its converted
+    /// `episodes`, and return the channel along with its image.
+    fn parse_channel<'a>(
+        &'a self,
+        dirpath: &'a Path,
+        file: Option<&'a ChannelFile>,
+        episodes: &'a [&'a Episode],
+    ) -> BoxFuture<'a, Result<(Channel, PathBuf)>>;
+}
+
+/// The file describing the channel of a channel directory, before parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelFile {
+    /// Filepath describing the channel.
+    pub filepath: PathBuf,
+    /// Other files describing the same channel, whose descriptions are merged into it.
+    pub merged: Vec<PathBuf>,
+    /// Warnings about the discovery, e.g. several candidate files.
+    pub warnings: Vec<String>,
+}
+
+impl ChannelFile {
+    /// Return the channel file at `filepath`, without merged files or warnings.
+    pub fn new<P: Into<PathBuf>>(filepath: P) -> Self {
+        Self {
+            filepath: filepath.into(),
+            merged: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// An episode found in a channel directory, before parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeFile {
+    /// Identifier of the episode, e.g. its YouTube ID, unique within the channel.
+    pub id: String,
+    /// Filepath describing the episode, e.g. its info file or its media file.
+    pub filepath: PathBuf,
+    /// Position of the episode among those discovered, starting at 1.
+    pub position: u32,
+    /// Warnings about the discovery, e.g. other files skipped for the same identifier.
+    pub warnings: Vec<String>,
+}
+
+/// Metadata of an episode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Episode {
+    /// Identifier of the episode, formatted into the GUID.
+    pub guid: String,
+    /// Publication date.
+    pub upload_date: NaiveDate,
+    /// Position of the episode in the channel.
+    pub playlist_index: u32,
+    /// Episode title.
+    pub title: String,
+    /// Episode webpage link, the public URL of the media file by default.
+    pub link: Option<String>,
+    /// Episode description.
+    pub description: String,
+    /// Author of the episode.
+    pub author: String,
+    /// Duration of the episode, if reported by the source.
+    pub duration_seconds: Option<u32>,
+    /// Live status, e.g. `not_live` or `was_live`, if reported by the source.
+    pub live_status: Option<String>,
+    /// Whether the episode was a live stream, if reported by the source.
+    pub was_live: Option<bool>,
+    /// Availability, e.g. `public` or `subscriber_only`, if reported by the source.
+    pub availability: Option<String>,
+    /// Keywords of the episode, e.g. its YouTube tags.
+    pub keywords: Vec<String>,
+    /// Minimum age of the audience, if reported by the source.
+    pub age_limit: Option<u32>,
+    /// Language of the episode, e.g. `en`, if reported by the source.
+    pub language: Option<String>,
+}
+
+impl Episode {
+    pub(crate) fn pub_date(&self) -> DateTime<Utc> {
+        let naived_date = self.upload_date.and_hms_opt(9, 10, 11).unwrap();
+        DateTime::<Utc>::from_naive_utc_and_offset(naived_date, Utc)
+    }
+}
+
+/// Metadata of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    /// Channel last publication date.
+    pub upload_date: NaiveDate,
+    /// Channel title.
+    pub title: String,
+    /// Channel description.
+    pub description: String,
+    /// Channel webpage link, the public URL of the channel directory by default.
+    pub link: Option<String>,
+    /// Author of the channel.
+    pub author: String,
+    /// Language of the channel, `en` by default.
+    pub language: Option<String>,
+}
+
+/// Channel metadata set in the `metadata` table of a channel directory, replacing the
+/// metadata parsed from its source:
+///
+/// ```toml
+/// [channels."Talks".metadata]
+/// title = "Conference talks"
+/// artwork = "talks.jpg"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overrides {
+    /// Channel title.
+    pub title: Option<String>,
+    /// Channel description.
+    pub description: Option<String>,
+    /// Author of the channel.
+    pub author: Option<String>,
+    /// Channel webpage link.
+    pub link: Option<String>,
+    /// Image of the channel, relative to the data directory.
+    pub artwork: Option<PathBuf>,
+}
+
+impl Overrides {
+    /// Replace the metadata of `channel`, and its image at `image_filepath`, with the
+    /// overrides that are set.
+    pub fn apply(&self, base_dir: &Path, channel: &mut Channel, image_filepath: &mut PathBuf) {
+        if let Some(title) = &self.title {
+            channel.title.clone_from(title);
+        }
+        if let Some(description) = &self.description {
+            channel.description.clone_from(description);
+        }
+        if let Some(author) = &self.author {
+            channel.author.clone_from(author);
+        }
+        if let Some(link) = &self.link {
+            channel.link = Some(link.clone());
+        }
+        if let Some(artwork) = &self.artwork {
+            *image_filepath = base_dir.join(artwork);
+        }
+    }
+}
+
+impl Channel {
+    pub(crate) fn pub_date(&self) -> DateTime<Utc> {
+        let naived_date = self.upload_date.and_hms_opt(9, 10, 11).unwrap();
+        DateTime::<Utc>::from_naive_utc_and_offset(naived_date, Utc)
+    }
+
+    pub(crate) fn language(&self) -> &str {
+        self.language.as_deref().unwrap_or("en")
+    }
+
+    pub(crate) fn last_build_date(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    pub(crate) fn generator(&self) -> &'static str {
+        "ytdlp"
+    }
+
+    pub(crate) fn explicit_content(&self) -> &'static str {
+        "false"
+    }
+
+    pub(crate) fn channel_type(&self) -> &'static str {
+        "Serial"
+    }
+}
+
+/// Represents the video file for an episode.
+#[derive(Debug, Clone)]
+pub struct Enclosure {
+    /// Path to the video file.
+    pub video_filepath: PathBuf,
+    /// File length of the video file.
+    pub video_filelength: u64,
+    /// MIME type of the video, e.g. `video/mp4` or `audio/mpeg`.
+    pub video_filetype: String,
+    /// Duration of the video file, if it could be probed.
+    pub duration_seconds: Option<u32>,
+    /// For MP4 files, whether the `moov` box precedes the media data.
+    pub faststart: Option<bool>,
+    /// Problems found while probing the video file.
+    pub warnings: Vec<String>,
+}
+
+/// Metadata sources, keyed by name.
+#[derive(Clone)]
+pub struct Sources(BTreeMap<String, Arc<dyn MetadataSource>>);
+
+impl Default for Sources {
+    /// Return the built-in sources, `yt-dlp` and `local`.
+    fn default() -> Self {
+        let mut sources = Self(BTreeMap::new());
+        sources.insert(DEFAULT, info::YtDlp::default());
+        sources.insert("local", local::AudioFiles);
+        sources
+    }
+}
+
+impl Sources {
+    /// Register `source` as `name`, replacing any source with that name.
+    pub fn insert(&mut self, name: impl Into<String>, source: impl MetadataSource + 'static) {
+        self.0.insert(name.into(), Arc::new(source));
+    }
+
+    /// Return the source selected by the settings of a channel, configured for the channel.
+    pub fn get(&self, settings: &ChannelSettings) -> Result<Arc<dyn MetadataSource>> {
+        let name = settings.source.as_deref().unwrap_or(DEFAULT);
+        match self.0.get(name) {
+            Some(source) => Ok(source.configure(settings).unwrap_or_else(|| source.clone())),
+            None => Err(Error::ConfigError(format!(
+                "unknown source `{name}`, expected one of: {}",
+                self.0.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Debug for Sources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A source with a single episode, described in memory.
+    struct Memory;
+
+    impl MetadataSource for Memory {
+        fn discover_channel<'a>(
+            &'a self,
+            _dirpath: &'a Path,
+        ) -> BoxFuture<'a, Result<Option<ChannelFile>>> {
+            Box::pin(async move { Ok(None) })
+        }
+
+        fn discover_episodes<'a>(
+            &'a self,
+            dirpath: &'a Path,
+        ) -> BoxFuture<'a, Result<Vec<EpisodeFile>>> {
+            Box::pin(async move {
+                Ok(vec![EpisodeFile {
+                    id: "first".into(),
+                    filepath: dirpath.join("first.mp3"),
+                    position: 1,
+                    warnings: Vec::new(),
+                }])
+            })
+        }
+
+        fn parse_episode<'a>(
+            &'a self,
+            file: &'a EpisodeFile,
+        ) -> BoxFuture<'a, Result<Option<(Episode, Enclosure, PathBuf)>>> {
+            Box::pin(async move {
+                let episode = Episode {
+                    guid: file.id.clone(),
+                    upload_date: NaiveDate::from_ymd_opt(2024, 2, 3).unwrap(),
+                    playlist_index: 1,
+                    title: "First".into(),
+                    link: None,
+                    description: "From memory".into(),
+                    author: "Author".into(),
+                    duration_seconds: Some(60),
+                    live_status: None,
+                    was_live: None,
+                    availability: None,
+                    keywords: Vec::new(),
+                    age_limit: None,
+                    language: None,
+                };
+                let enclosure = Enclosure {
+                    video_filepath: file.filepath.clone(),
+                    video_filelength: 4,
+                    video_filetype: "audio/mpeg".into(),
+                    duration_seconds: None,
+                    faststart: None,
+                    warnings: Vec::new(),
+                };
+                Ok(Some((
+                    episode,
+                    enclosure,
+                    file.filepath.with_extension("jpg"),
+                )))
+            })
+        }
+
+        fn parse_channel<'a>(
+            &'a self,
+            dirpath: &'a Path,
+            _file: Option<&'a ChannelFile>,
+            episodes: &'a [&'a Episode],
+        ) -> BoxFuture<'a, Result<(Channel, PathBuf)>> {
+            Box::pin(async move {
+                let channel = Channel {
+                    upload_date: episodes[0].upload_date,
+                    title: "Memory".into(),
+                    description: String::new(),
+                    link: None,
+                    author: "Author".into(),
+                    language: None,
+                };
+                Ok((channel, dirpath.join("folder.jpg")))
+            })
+        }
+    }
+
+    #[test]
+    fn sources_are_selected_by_name() {
+        let mut sources = Sources::default();
+        sources.insert("memory", Memory);
+        assert_eq!(format!("{sources:?}"), r#"{"local", "memory", "yt-dlp"}"#);
+
+        let settings = |source: Option<&str>| ChannelSettings {
+            source: source.map(String::from),
+            ..ChannelSettings::default()
+        };
+        assert!(sources.get(&settings(None)).is_ok());
+        assert!(sources.get(&settings(Some("memory"))).is_ok());
+        let error = sources.get(&settings(Some("database"))).err().unwrap();
+        assert!(matches!(error, Error::ConfigError(msg) if msg.contains("local, memory, yt-dlp")));
+    }
+
+    #[test]
+    fn custom_sources_are_converted() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let channel_dir = tmp.path().join("Memory");
+        std::fs::create_dir(&channel_dir).unwrap();
+        std::fs::write(channel_dir.join("first.mp3"), [0_u8; 4]).unwrap();
+        for filename in ["first.jpg", "folder.jpg"] {
+            image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]))
+                .save(channel_dir.join(filename))
+                .unwrap();
+        }
+
+        let base_url = crate::convert::parse_base_url("https://cdn.example.com").unwrap();
+        let (feed, stats) = smol::block_on(crate::convert::process_split(
+            &Memory,
+            tmp.path(),
+            &channel_dir,
+            &base_url,
+            &ChannelSettings::default(),
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(stats.episodes, 1);
+        assert_eq!(feed.channel.title, "Memory");
+        assert_eq!(feed.channel.link, "https://cdn.example.com/Memory");
+        let episode = &feed.channel.episodes[0];
+        assert_eq!(episode.link, "https://cdn.example.com/Memory/first.mp3");
+        assert_eq!(episode.pub_date, "Sat, 03 Feb 2024 09:10:11 +0000");
+    }
+}
@@ -0,0 +1,118 @@
+//! Feeds split off a channel directory.
+//!
+//! Each split feed takes the episodes matching its rule, which leave the main feed of the
+//! channel. An episode matching several rules goes to the first split:
+//!
+//! ```toml
+//! [[channels."Talk show".split]]
+//! name = "Talk show clips"
+//! title_suffix = " (clips)"
+//! artwork = "clips.jpg"
+//! rule = { max_duration = 600 }
+//! ```
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{filter, source::Episode};
+
+/// A feed split off a channel directory.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Split {
+    /// Name of the feed, written as `<name>.xml` next to the channel feed.
+    #[serde(deserialize_with = "deserialize_name")]
+    pub name: String,
+    /// Rule matching the episodes of the feed.
+    pub rule: filter::Rule,
+    /// Suffix appended to the title of the channel.
+    #[serde(default)]
+    pub title_suffix: String,
+    /// Image of the feed, relative to the data directory, instead of the channel image.
+    pub artwork: Option<PathBuf>,
+}
+
+/// Return the split of `splits` the episode described by `info` belongs to, or `None` if it
+/// belongs to the main feed.
+pub fn select<'a>(splits: &'a [Split], info: &Episode) -> Option<&'a Split> {
+    splits.iter().find(|split| split.rule.matches(info))
+}
+
+/// Return `true` if `name` is a plain filename: not empty, `.` or `..`, and without path
+/// separators. Feeds named after it are written next to the channel directories.
+pub(crate) fn is_filename(name: &str) -> bool {
+    !(name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']))
+}
+
+/// Deserialize the name of a split feed, which must be a plain filename.
+fn deserialize_name<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    if !is_filename(&name) {
+        return Err(serde::de::Error::custom(format!(
+            "invalid split name `{name}`: must be a filename without path separators"
+        )));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, settings::Settings};
+
+    #[test]
+    fn episodes_go_to_the_first_matching_split() {
+        let splits: Vec<Split> = toml::from_str::<toml::Table>(
+            r#"
+            [[split]]
+            name = "Clips"
+            rule = { max_duration = 600 }
+
+            [[split]]
+            name = "Trailers"
+            rule = { title = "(?i)trailer" }
+            "#,
+        )
+        .unwrap()["split"]
+            .clone()
+            .try_into()
+            .unwrap();
+
+        let info = |title: &str, duration: u32| -> Episode {
+            serde_json::from_value::<crate::info::episode::Info>(serde_json::json!({
+                "id": "dQw4w9WgXcQ",
+                "upload_date": "20230519",
+                "playlist_index": 1,
+                "title": title,
+                "webpage_url": "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                "description": "A test episode",
+                "channel": "Author",
+                "duration": duration,
+            }))
+            .unwrap()
+            .try_into()
+            .unwrap()
+        };
+        let name = |info: &Episode| select(&splits, info).map(|split| split.name.as_str());
+        assert_eq!(name(&info("Episode", 3600)), None);
+        assert_eq!(name(&info("Trailer", 60)), Some("Clips"));
+        assert_eq!(name(&info("Trailer", 3600)), Some("Trailers"));
+    }
+
+    #[test]
+    fn split_names_must_be_filenames() {
+        for name in ["", ".", "..", "../feeds", "clips/short", "clips\\short"] {
+            let settings = Settings::from_toml(&format!(
+                "[[channels.\"Talk show\".split]]\nname = '{name}'\nrule = {{ max_duration = 600 }}"
+            ));
+            assert!(
+                matches!(&settings, Err(Error::ConfigError(msg)) if msg.contains("invalid split name")),
+                "{name}: {settings:?}"
+            );
+        }
+    }
+}
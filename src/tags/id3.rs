@@ -0,0 +1,227 @@
+//! ID3v2.3 and ID3v2.4 tags, at the start of MP3 files.
+
+use std::io::Read;
+
+use super::{Cover, Tags, invalid};
+use crate::{Result, probe::read_up_to};
+
+/// Largest tag read in memory.
+const MAX_TAG_SIZE: usize = 64 * 1024 * 1024;
+
+/// Picture type of the front cover in `APIC` frames.
+const FRONT_COVER: u8 = 3;
+
+/// Read the ID3v2 tag at the start of `reader`, or return empty tags if there is none.
+pub fn read<R: Read>(reader: &mut R) -> Result<Tags> {
+    let mut header = [0_u8; 10];
+    if read_up_to(reader, &mut header)? < 10 || !header.starts_with(b"ID3") {
+        return Ok(Tags::default());
+    }
+    let version = header[3];
+    if !(3..=4).contains(&version) {
+        return Err(invalid("unsupported ID3v2 version"));
+    }
+    let size = synchsafe(&header[6..10]);
+    if size > MAX_TAG_SIZE {
+        return Err(invalid("ID3v2 tag too large"));
+    }
+    let mut tag = vec![0_u8; size];
+    reader.read_exact(&mut tag)?;
+    if header[5] & 0x80 != 0 {
+        tag = resynchronise(&tag);
+    }
+
+    let mut offset = 0;
+    if header[5] & 0x40 != 0 {
+        // Skip the extended header, whose size includes itself in ID3v2.4 only.
+        let size = tag.get(..4).ok_or_else(|| invalid("truncated ID3v2 tag"))?;
+        offset = match version {
+            4 => synchsafe(size),
+            _ => 4 + u32::from_be_bytes(size.try_into().unwrap()) as usize,
+        };
+    }
+
+    let mut tags = Tags::default();
+    let mut covers = Vec::new();
+    let (mut year, mut day_month) = (None, None);
+    while offset + 10 <= tag.len() && tag[offset] != 0 {
+        let id = &tag[offset..offset + 4];
+        let size = match version {
+            4 => synchsafe(&tag[offset + 4..offset + 8]),
+            _ => u32::from_be_bytes(tag[offset + 4..offset + 8].try_into().unwrap()) as usize,
+        };
+        let payload = tag
+            .get(offset + 10..offset + 10 + size)
+            .ok_or_else(|| invalid("truncated ID3v2 frame"))?;
+        offset += 10 + size;
+
+        match id {
+            b"TIT2" => tags.title = text(payload),
+            b"TPE1" => tags.artist = text(payload),
+            b"TPE2" if tags.artist.is_none() => tags.artist = text(payload),
+            b"TALB" => tags.album = text(payload),
+            b"TDRC" => tags.date = text(payload),
+            b"TYER" => year = text(payload),
+            b"TDAT" => day_month = text(payload),
+            b"TRCK" => tags.track = text(payload).and_then(|track| super::track(&track)),
+            b"APIC" => covers.extend(picture(payload)),
+            _ => {}
+        }
+    }
+
+    // ID3v2.3 splits the date into the year and the day and month, as DDMM.
+    if tags.date.is_none() {
+        tags.date = match (year, day_month) {
+            (Some(year), Some(dm)) if dm.len() == 4 => {
+                Some(format!("{year}-{}-{}", &dm[2..4], &dm[..2]))
+            }
+            (year, _) => year,
+        };
+    }
+    covers.sort_by_key(|(picture_type, _)| *picture_type != FRONT_COVER);
+    tags.cover = covers.into_iter().next().map(|(_, cover)| cover);
+    Ok(tags)
+}
+
+/// Return the integer stored in 4 bytes of 7 bits.
+fn synchsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, b| (size << 7) | (*b & 0x7F) as usize)
+}
+
+/// Remove the zero bytes inserted after `0xFF` bytes by the unsynchronisation scheme.
+fn resynchronise(bytes: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(bytes.len());
+    for (index, byte) in bytes.iter().enumerate() {
+        if *byte == 0 && index > 0 && bytes[index - 1] == 0xFF {
+            continue;
+        }
+        output.push(*byte);
+    }
+    output
+}
+
+/// Return the first value of a text frame, unless it is empty.
+fn text(payload: &[u8]) -> Option<String> {
+    let (encoding, bytes) = payload.split_first()?;
+    let (value, _) = decode(*encoding, bytes);
+    let value = value.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Return the picture type and the picture of an `APIC` frame.
+fn picture(payload: &[u8]) -> Option<(u8, Cover)> {
+    let (encoding, rest) = payload.split_first()?;
+    let mime_end = rest.iter().position(|b| *b == 0)?;
+    let picture_type = *rest.get(mime_end + 1)?;
+    let (_, description_size) = decode(*encoding, &rest[mime_end + 2..]);
+    let data = rest.get(mime_end + 2 + description_size..)?;
+    Some((picture_type, Cover::new(data.to_vec())?))
+}
+
+/// Decode the string at the start of `bytes` with the ID3v2 `encoding`, and return it with
+/// the number of bytes it spans, terminator included.
+fn decode(encoding: u8, bytes: &[u8]) -> (String, usize) {
+    match encoding {
+        1 | 2 => {
+            let end = bytes
+                .chunks_exact(2)
+                .position(|unit| unit == [0, 0])
+                .map_or(bytes.len() & !1, |index| index * 2);
+            let mut units = bytes[..end].chunks_exact(2);
+            let big_endian = match bytes.get(..2) {
+                Some([0xFF, 0xFE]) => {
+                    units.next();
+                    false
+                }
+                Some([0xFE, 0xFF]) => {
+                    units.next();
+                    true
+                }
+                _ => encoding == 2,
+            };
+            let units: Vec<u16> = units
+                .map(|unit| match big_endian {
+                    true => u16::from_be_bytes([unit[0], unit[1]]),
+                    false => u16::from_le_bytes([unit[0], unit[1]]),
+                })
+                .collect();
+            (String::from_utf16_lossy(&units), (end + 2).min(bytes.len()))
+        }
+        _ => {
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+            let value = match encoding {
+                3 => String::from_utf8_lossy(&bytes[..end]).into_owned(),
+                _ => bytes[..end].iter().map(|b| *b as char).collect(),
+            };
+            (value, (end + 1).min(bytes.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Return an ID3v2.3 frame.
+    pub(crate) fn frame(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend((payload.len() as u32).to_be_bytes());
+        frame.extend([0, 0]);
+        frame.extend(payload);
+        frame
+    }
+
+    /// Return an ID3v2.3 tag with `frames`.
+    pub(crate) fn tag(frames: &[Vec<u8>]) -> Vec<u8> {
+        let frames = frames.concat();
+        let size = frames.len();
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend([21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8));
+        tag.extend(frames);
+        tag
+    }
+
+    #[test]
+    fn text_frames_and_cover_are_read() {
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("Ünïcode".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let mut cover = b"\x00image/jpeg\x00\x03front\x00".to_vec();
+        cover.extend([0xFF, 0xD8, 0xFF, 0xE0]);
+        let bytes = tag(&[
+            frame(b"TIT2", &[[1].as_slice(), &utf16].concat()),
+            frame(b"TPE1", b"\x03Artist\x00"),
+            frame(b"TALB", b"\x00Album"),
+            frame(b"TYER", b"\x002021"),
+            frame(b"TDAT", b"\x000503"),
+            frame(b"TRCK", b"\x007/12"),
+            frame(b"APIC", &cover),
+        ]);
+
+        let tags = read(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Ünïcode"));
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(tags.album.as_deref(), Some("Album"));
+        assert_eq!(tags.date.as_deref(), Some("2021-03-05"));
+        assert_eq!(tags.track, Some(7));
+        let cover = tags.cover.unwrap();
+        assert_eq!(cover.extension, "jpg");
+        assert_eq!(cover.data, [0xFF, 0xD8, 0xFF, 0xE0]);
+    }
+
+    #[test]
+    fn missing_or_truncated_tags() {
+        assert_eq!(
+            read(&mut Cursor::new(b"\xFF\xFB\x90\x00")).unwrap(),
+            Tags::default()
+        );
+        let mut truncated = tag(&[frame(b"TIT2", b"\x00Title")]);
+        truncated.truncate(truncated.len() - 2);
+        assert!(read(&mut Cursor::new(truncated)).is_err());
+    }
+}
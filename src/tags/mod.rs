@@ -0,0 +1,138 @@
+//! Read the tags of audio files.
+//!
+//! Supports ID3v2.3 and ID3v2.4 tags in MP3 files, and iTunes-style metadata in MP4/M4A
+//! files, identified by their content rather than their extension.
+
+use std::{
+    fs::File,
+    io::{BufReader, Seek, SeekFrom},
+    path::Path,
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    Result,
+    error::Error,
+    probe::{self, read_up_to},
+};
+
+pub mod id3;
+pub mod mp4;
+
+/// Tags of an audio file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    /// Title of the track.
+    pub title: Option<String>,
+    /// Artist, or album artist if there is no artist.
+    pub artist: Option<String>,
+    /// Album.
+    pub album: Option<String>,
+    /// Recording or release date, e.g. `2021` or `2021-03-05`.
+    pub date: Option<String>,
+    /// Track number.
+    pub track: Option<u32>,
+    /// Embedded cover art.
+    pub cover: Option<Cover>,
+}
+
+/// An embedded picture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cover {
+    /// Extension of the picture format, `jpg` or `png`.
+    pub extension: &'static str,
+    /// Content of the picture file.
+    pub data: Vec<u8>,
+}
+
+impl Cover {
+    /// Return the picture with the content `data`, unless it is neither JPEG nor PNG.
+    pub fn new(data: Vec<u8>) -> Option<Self> {
+        let extension = if data.starts_with(&[0xFF, 0xD8]) {
+            "jpg"
+        } else if data.starts_with(b"\x89PNG") {
+            "png"
+        } else {
+            return None;
+        };
+        Some(Self { extension, data })
+    }
+}
+
+impl Tags {
+    /// Return the date, whose month and day default to January 1st.
+    pub fn date(&self) -> Option<NaiveDate> {
+        let date = self.date.as_deref()?;
+        let number = |range: std::ops::Range<usize>| date.get(range)?.parse::<u32>().ok();
+        let year = number(0..4)? as i32;
+        let month = number(5..7).unwrap_or(1);
+        let day = number(8..10).unwrap_or(1);
+        NaiveDate::from_ymd_opt(year, month, day).or_else(|| NaiveDate::from_ymd_opt(year, 1, 1))
+    }
+}
+
+/// Read the tags of the audio file at `filepath`.
+pub fn read<P: AsRef<Path>>(filepath: P) -> Result<Tags> {
+    let filepath = filepath.as_ref();
+    let mut reader = BufReader::new(File::open(filepath)?);
+
+    let mut magic = [0_u8; 8];
+    let read = read_up_to(&mut reader, &mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+    let magic = &magic[..read];
+
+    let tags = if magic.len() >= 8 && probe::mp4::is_box_type(&magic[4..8]) {
+        mp4::read(&mut reader)
+    } else {
+        id3::read(&mut reader)
+    };
+
+    tags.map_err(|e| match e {
+        Error::Probe(_, reason) | Error::Tags(_, reason) => Error::Tags(filepath.into(), reason),
+        e => e,
+    })
+}
+
+/// Return a tag error; the filepath is filled in by [`read`].
+fn invalid(reason: &str) -> Error {
+    Error::Tags(Default::default(), reason.into())
+}
+
+/// Return the track number of a `track` or `track/total` value.
+fn track(value: &str) -> Option<u32> {
+    let track = value.split('/').next()?.trim().parse().ok()?;
+    (track > 0).then_some(track)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_and_track_numbers_are_parsed() {
+        let tags = |date: &str| Tags {
+            date: Some(date.into()),
+            ..Tags::default()
+        };
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(tags("2021").date(), date(2021, 1, 1));
+        assert_eq!(tags("2021-03-05T10:00:00Z").date(), date(2021, 3, 5));
+        assert_eq!(tags("2021-13-45").date(), date(2021, 1, 1));
+        assert_eq!(tags("unknown").date(), None);
+
+        assert_eq!(track("7/12"), Some(7));
+        assert_eq!(track("3"), Some(3));
+        assert_eq!(track("0"), None);
+    }
+
+    #[test]
+    fn read_reports_the_filepath() {
+        let directory = tempfile::tempdir().unwrap();
+        let filepath = directory.path().join("track.mp3");
+        std::fs::write(&filepath, b"ID3\x02\x00\x00\x00\x00\x00\x00").unwrap();
+
+        let error = read(&filepath).unwrap_err();
+        assert!(matches!(error, Error::Tags(path, _) if path == filepath));
+    }
+}
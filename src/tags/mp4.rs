@@ -0,0 +1,118 @@
+//! iTunes-style metadata of MP4/M4A files, in `moov/udta/meta/ilst`.
+
+use std::io::{Read, Seek};
+
+use super::{Cover, Tags};
+use crate::{
+    Result,
+    probe::mp4::{children, read_moov, top_level_boxes},
+};
+
+/// Read the metadata of an MP4 file, or return empty tags if there is none.
+pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Tags> {
+    let boxes = top_level_boxes(reader)?;
+    let Some(moov) = boxes.iter().find(|b| &b.box_type == b"moov") else {
+        return Ok(Tags::default());
+    };
+    let moov = read_moov(reader, moov)?;
+
+    let mut tags = Tags::default();
+    let Some(ilst) = child(&moov, b"udta")
+        .and_then(|udta| child(udta, b"meta"))
+        .and_then(meta_children)
+        .and_then(|meta| meta.into_iter().find(|(t, _)| t == b"ilst"))
+    else {
+        return Ok(tags);
+    };
+
+    for (item_type, item) in children(ilst.1)? {
+        let Some((data_type, value)) = data(item) else {
+            continue;
+        };
+        let text = || {
+            let value = String::from_utf8_lossy(value).trim().to_string();
+            (data_type == 1 && !value.is_empty()).then_some(value)
+        };
+        match &item_type {
+            b"\xA9nam" => tags.title = text(),
+            b"\xA9ART" => tags.artist = text(),
+            b"aART" if tags.artist.is_none() => tags.artist = text(),
+            b"\xA9alb" => tags.album = text(),
+            b"\xA9day" => tags.date = text(),
+            b"trkn" => {
+                tags.track = value
+                    .get(2..4)
+                    .map(|track| u16::from_be_bytes(track.try_into().unwrap()) as u32)
+                    .filter(|track| *track > 0);
+            }
+            b"covr" if tags.cover.is_none() => tags.cover = Cover::new(value.to_vec()),
+            _ => {}
+        }
+    }
+    Ok(tags)
+}
+
+/// Return the payload of the first child box of type `box_type` in `payload`.
+fn child<'a>(payload: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    children(payload)
+        .ok()?
+        .into_iter()
+        .find(|(t, _)| t == box_type)
+        .map(|(_, payload)| payload)
+}
+
+/// Return the children of a `meta` box, which is a full box in MP4 files but not in
+/// QuickTime files.
+fn meta_children(meta: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+    match meta.get(4..8) {
+        Some(b"hdlr") => children(meta).ok(),
+        _ => children(meta.get(4..)?).ok(),
+    }
+}
+
+/// Return the type and the value of the `data` box of a metadata item.
+fn data(item: &[u8]) -> Option<(u32, &[u8])> {
+    let data = child(item, b"data")?;
+    let data_type = u32::from_be_bytes(data.get(..4)?.try_into().unwrap()) & 0x00FF_FFFF;
+    Some((data_type, data.get(8..)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::probe::mp4::tests::mp4_box;
+
+    fn item(item_type: &[u8; 4], data_type: u8, value: &[u8]) -> Vec<u8> {
+        let data = [&[0, 0, 0, data_type], &[0; 4][..], value].concat();
+        mp4_box(item_type, &mp4_box(b"data", &data))
+    }
+
+    #[test]
+    fn ilst_items_are_read() {
+        let ilst = [
+            item(b"\xA9nam", 1, b"Title"),
+            item(b"aART", 1, b"Album artist"),
+            item(b"\xA9alb", 1, b"Album"),
+            item(b"\xA9day", 1, b"2021-03-05T00:00:00Z"),
+            item(b"trkn", 0, &[0, 0, 0, 7, 0, 12, 0, 0]),
+            item(b"covr", 14, b"\x89PNG\r\n\x1a\n"),
+        ]
+        .concat();
+        let meta = [&[0; 4][..], &mp4_box(b"ilst", &ilst)].concat();
+        let moov = mp4_box(b"moov", &mp4_box(b"udta", &mp4_box(b"meta", &meta)));
+        let file = [mp4_box(b"ftyp", b"M4A "), moov].concat();
+
+        let tags = read(&mut Cursor::new(file)).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Title"));
+        assert_eq!(tags.artist.as_deref(), Some("Album artist"));
+        assert_eq!(tags.album.as_deref(), Some("Album"));
+        assert_eq!(tags.date.as_deref(), Some("2021-03-05T00:00:00Z"));
+        assert_eq!(tags.track, Some(7));
+        assert_eq!(tags.cover.unwrap().extension, "png");
+
+        let untagged = [mp4_box(b"ftyp", b"M4A "), mp4_box(b"moov", &[])].concat();
+        assert_eq!(read(&mut Cursor::new(untagged)).unwrap(), Tags::default());
+    }
+}
@@ -0,0 +1,99 @@
+//! Output formats of the feeds.
+//!
+//! A [`FeedWriter`] renders the channel converted from a channel directory, with its
+//! episodes, into the content of a feed file. RSS is the built-in format, see [`RssWriter`];
+//! other formats are added with
+//! [`FeedGeneratorBuilder::writer`](crate::generator::FeedGeneratorBuilder::writer), and each
+//! feed is then written once per writer, as `<name>.<extension>`.
+//!
+//! Writers stream the feed into a buffered file as they serialize it, which avoids the extra
+//! `String` of the rendered feed and the copies of its prolog. The converted channel they
+//! serialize is still held whole in memory.
+
+use std::io::Write;
+
+use hard_xml::{XmlWrite, XmlWriter};
+
+use crate::{Result, rss::channel::Channel};
+
+/// Renders channels into feed files.
+pub trait FeedWriter: Send + Sync {
+    /// Extension of the feed files, without the dot, e.g. `xml`.
+    fn extension(&self) -> &str;
+
+    /// MIME type of the feed files, e.g. `application/rss+xml`.
+    fn mime_type(&self) -> &str;
+
+    /// Write the content of the feed file of `channel` to `output`, as it is serialized.
+    fn write(&self, channel: &Channel, output: &mut dyn Write) -> Result<()>;
+
+    /// Return the content of the feed file of `channel`.
+    fn render(&self, channel: &Channel) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.write(channel, &mut content)?;
+        Ok(content)
+    }
+}
+
+/// The RSS 2.0 format, with the iTunes podcast extensions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RssWriter;
+
+impl FeedWriter for RssWriter {
+    fn extension(&self) -> &str {
+        "xml"
+    }
+
+    fn mime_type(&self) -> &str {
+        "application/rss+xml"
+    }
+
+    fn write(&self, channel: &Channel, output: &mut dyn Write) -> Result<()> {
+        let mut writer = XmlWriter::new(output);
+        writeln!(writer.inner, "{}", crate::rss::XML_PROLOG)?;
+        writer.write_element_start("rss")?;
+        writer.write_attribute("version", crate::rss::VERSION)?;
+        writer.write_attribute("xmlns:itunes", crate::rss::ITUNES_NAMESPACE)?;
+        writer.write_attribute("xmlns:content", crate::rss::CONTENT_NAMESPACE)?;
+        writer.write_element_end_open()?;
+        channel.to_writer(&mut writer)?;
+        writer.write_element_end_close("rss")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert, rss::channel::Image};
+
+    #[test]
+    fn rss_writer_matches_the_rendered_feed() {
+        let channel = || Channel {
+            title: "Title & more".into(),
+            description: "Description".into(),
+            link: "https://example.com".into(),
+            image: Image {
+                image_url: "https://example.com/image.jpg".into(),
+            },
+            author: "Author".into(),
+            language: "en".into(),
+            last_build_date: "Sun, 01 Jan 2023 09:10:11 +0000".into(),
+            pub_date: "Sun, 01 Jan 2023 09:10:11 +0000".into(),
+            categories: vec![crate::rss::channel::Category {
+                text: "Technology".into(),
+                subcategories: Vec::new(),
+            }],
+            generator: "ytdlp".into(),
+            explicit_content: "false".into(),
+            channel_type: "Serial".into(),
+            episodes: Vec::new(),
+        };
+
+        let written = RssWriter.render(&channel()).unwrap();
+        let rendered = convert::render(&convert::feed(channel())).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), rendered);
+        assert_eq!(RssWriter.extension(), "xml");
+        assert_eq!(RssWriter.mime_type(), "application/rss+xml");
+    }
+}